@@ -0,0 +1,152 @@
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, Timelike, Weekday};
+
+/// How times are written to file, local time to the second
+pub const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// How times are shown to users, local time to the second
+pub const DISPLAY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn now() -> NaiveDateTime {
+    //! The local time, without the fractions of a second the file format does not keep
+    let now = Local::now().naive_local();
+
+    now.with_nanosecond(0).unwrap_or(now)
+}
+
+pub fn format_age(since: NaiveDateTime, now: NaiveDateTime) -> String {
+    //! How long ago since was in its largest whole unit, e.g. 5m, 3h or 12d.
+    let age = now.signed_duration_since(since);
+
+    match age.num_minutes() {
+        minutes if minutes < 60 => format!("{}m", minutes.max(0)),
+        _ if age.num_hours() < 24 => format!("{}h", age.num_hours()),
+        _ => format!("{}d", age.num_days()),
+    }
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+    match input {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+pub fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    //! Accepts a full date (2026-11-01), today, tomorrow, yesterday, or a day of the week (fri,
+    //! friday) meaning the next such day after today.
+    let input = input.trim().to_ascii_lowercase();
+
+    match input.as_str() {
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        "yesterday" => today.pred_opt(),
+        _ => match parse_weekday(&input) {
+            Some(weekday) => {
+                let days_ahead = (weekday.num_days_from_monday() + 7
+                    - today.weekday().num_days_from_monday())
+                    % 7;
+
+                // The same day of the week as today means a week from today
+                today.checked_add_days(Days::new(match days_ahead {
+                    0 => 7,
+                    days => days as u64,
+                }))
+            }
+            None => NaiveDate::parse_from_str(&input, "%Y-%m-%d").ok(),
+        },
+    }
+}
+
+pub fn parse_past_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    //! Like parse_date, but a day of the week means the last such day before today, for looking
+    //! back at what has been done.
+    match parse_weekday(&input.trim().to_ascii_lowercase()) {
+        Some(weekday) => {
+            let days_back =
+                (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+
+            today.checked_sub_days(Days::new(match days_back {
+                0 => 7,
+                days => days as u64,
+            }))
+        }
+        None => parse_date(input, today),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parse_past_dates() {
+        // A Sunday
+        let today = date(2026, 10, 18);
+
+        assert_eq!(Some(date(2026, 10, 12)), parse_past_date("mon", today));
+        assert_eq!(Some(date(2026, 10, 17)), parse_past_date("Saturday", today));
+        assert_eq!(Some(date(2026, 10, 11)), parse_past_date("sun", today));
+        assert_eq!(
+            Some(date(2026, 10, 17)),
+            parse_past_date("yesterday", today)
+        );
+        assert_eq!(Some(date(2026, 9, 1)), parse_past_date("2026-09-01", today));
+    }
+
+    #[test]
+    fn ages() {
+        let now = date(2026, 10, 18).and_hms_opt(12, 0, 0).unwrap();
+
+        assert_eq!("0m", format_age(now, now));
+        assert_eq!(
+            "0m",
+            format_age(date(2026, 10, 19).and_hms_opt(0, 0, 0).unwrap(), now)
+        );
+        assert_eq!(
+            "59m",
+            format_age(date(2026, 10, 18).and_hms_opt(11, 1, 0).unwrap(), now)
+        );
+        assert_eq!(
+            "23h",
+            format_age(date(2026, 10, 17).and_hms_opt(12, 0, 1).unwrap(), now)
+        );
+        assert_eq!(
+            "3d",
+            format_age(date(2026, 10, 15).and_hms_opt(9, 0, 0).unwrap(), now)
+        );
+    }
+
+    #[test]
+    fn parse_relative_dates() {
+        // A Sunday
+        let today = date(2026, 10, 18);
+
+        assert_eq!(Some(today), parse_date("today", today));
+        assert_eq!(Some(date(2026, 10, 19)), parse_date("Tomorrow", today));
+        assert_eq!(Some(date(2026, 10, 23)), parse_date("fri", today));
+        assert_eq!(Some(date(2026, 10, 25)), parse_date("sunday", today));
+    }
+
+    #[test]
+    fn parse_full_dates() {
+        let today = date(2026, 10, 18);
+
+        assert_eq!(Some(date(2026, 11, 1)), parse_date("2026-11-01", today));
+        assert_eq!(None, parse_date("2026-13-01", today));
+        assert_eq!(None, parse_date("someday", today));
+    }
+}
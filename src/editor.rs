@@ -0,0 +1,48 @@
+use std::{env, fs, io::Write, process::Command};
+
+use crate::error::WhatodoError;
+
+type Result<T> = std::result::Result<T, WhatodoError>;
+
+fn editor_command() -> String {
+    // Same lookup order as git, with vi as the last resort
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+pub fn edit_text(initial: &str) -> Result<String> {
+    //! Opens initial in the user's editor and returns the saved text once the editor exits.
+    //! The editor is run by the shell like git does, so it may be given with arguments, e.g.
+    //! EDITOR="code --wait".
+    let mut file = tempfile::Builder::new()
+        .prefix("whatodo-")
+        .suffix(".txt")
+        .tempfile()
+        .map_err(WhatodoError::CannotOpenEditor)?;
+
+    file.write_all(initial.as_bytes())
+        .and_then(|_| file.flush())
+        .map_err(WhatodoError::CannotOpenEditor)?;
+
+    let editor = editor_command();
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(&editor)
+        .arg(file.path())
+        .status();
+
+    // The file is removed once it is dropped
+    match status {
+        Ok(status) if status.success() => fs::read_to_string(file.path()),
+        Ok(status) => Err(std::io::Error::other(format!(
+            "{editor} exited with {status}"
+        ))),
+        Err(e) => Err(e),
+    }
+    .map_err(WhatodoError::CannotOpenEditor)
+}
@@ -7,6 +7,23 @@ pub enum WhatodoError {
     CannotSaveTodos(std::io::Error),
     InvalidCommand,
     NotEnoughArguments,
+    CannotParseTodos(ParseError),
+    NoListFound,
+    NoGlobalList,
+    NoGlobalListFound,
+    CannotOpenEditor(std::io::Error),
+    EmptyTodo,
+    InvalidPriority(String),
+    InvalidDate(String),
+    NoSuchId(String),
+    InvalidPattern(regex::Error),
+    InvalidFilter(ParseError),
+    InvalidPath(String),
+    CannotLoadHistory(std::io::Error),
+    CannotSaveHistory(std::io::Error),
+    InvalidHistory(ParseError),
+    NothingToUndo,
+    NothingToRedo,
 }
 
 impl std::fmt::Display for WhatodoError {
@@ -15,12 +32,68 @@ impl std::fmt::Display for WhatodoError {
             Self::IndexOutOfBounds => write!(f, "Index out of bounds, could not perform operation"),
             Self::CannotLoadTodos(e) => write!(f, "Could not load todos: {e}"),
             Self::CannotInitTodos(e) => write!(f, "Could not init whatodo: {e}"),
-            Self::TodoAlreadyInList => write!(f, "Todo is already in list, could not add todo"),
+            Self::TodoAlreadyInList => write!(f, "Todo is already in list"),
             Self::CannotSaveTodos(e) => write!(f, "Could not save todos: {e}"),
             Self::InvalidCommand => write!(f, "Invalid command"),
             Self::NotEnoughArguments => write!(f, "Not enough arguments provided"),
+            Self::CannotParseTodos(e) => write!(f, "Could not parse todos: {e}"),
+            Self::NoListFound => write!(
+                f,
+                "No todo.todos found in this directory or any parent, run whatodo init to create one"
+            ),
+            Self::CannotOpenEditor(e) => write!(f, "Could not edit in editor: {e}"),
+            Self::InvalidPriority(p) => write!(f, "Invalid priority {p}, expected A, B, C or none"),
+            Self::InvalidDate(d) => write!(
+                f,
+                "Invalid date {d}, expected YYYY-MM-DD, today, tomorrow or a day of the week"
+            ),
+            Self::InvalidPath(p) => write!(
+                f,
+                "Invalid path {p}, expected indices separated by dots such as 2.1.3 or -1"
+            ),
+            Self::InvalidFilter(e) => write!(
+                f,
+                "Invalid filter at column {}: expected {}, found {}",
+                e.column, e.expected, e.found
+            ),
+            Self::InvalidPattern(e) => write!(f, "Invalid regex: {e}"),
+            Self::CannotLoadHistory(e) => write!(f, "Could not load history: {e}"),
+            Self::CannotSaveHistory(e) => write!(f, "Could not save history: {e}"),
+            Self::InvalidHistory(e) => write!(f, "Could not parse history: {e}"),
+            Self::NothingToUndo => write!(f, "Nothing to undo"),
+            Self::NothingToRedo => write!(f, "Nothing to redo"),
+            Self::NoSuchId(id) => write!(f, "No todo has the id {id}"),
+            Self::EmptyTodo => write!(f, "Todo contents cannot be empty"),
+            Self::NoGlobalListFound => write!(
+                f,
+                "There is no global list yet, run whatodo --global init to create one"
+            ),
+            Self::NoGlobalList => write!(
+                f,
+                "Could not locate the global list, neither XDG_DATA_HOME nor HOME is set"
+            ),
         }
     }
 }
 
 impl std::error::Error for WhatodoError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
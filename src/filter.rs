@@ -0,0 +1,489 @@
+// Filters select todos with expressions like
+//   status:open and (tag:api or priority:A) and due<7d
+// Conditions are field, comparison, value with no spaces between them, combined with and, or, not
+// and parentheses. and binds tighter than or.
+
+use chrono::{Days, NaiveDate};
+
+use crate::{date, error::ParseError, todo::Priority, todo::Todo};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Open,
+    Done,
+    /// Open with some completed work below it, see Todo::is_partial
+    Partial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Due,
+    Created,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn compare<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+        }
+    }
+}
+
+/// A parsed filter expression, see Filter::parse
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Status(Status),
+    Tag(String),
+    Context(String),
+    /// None matches todos without a priority
+    Priority(Option<Priority>),
+    /// None matches todos without the date
+    Date(DateField, Comparison, Option<NaiveDate>),
+    /// Case insensitive text found in the contents or notes
+    Text(String),
+    Id(String),
+}
+
+impl Filter {
+    pub fn parse(input: &str, today: NaiveDate) -> Result<Self, ParseError> {
+        //! Relative dates such as due<7d or done>-1w are resolved against today.
+        let tokens = tokenize_filter(input)?;
+
+        FilterParser {
+            tokens: &tokens,
+            position: 0,
+            today,
+        }
+        .parse_filter()
+    }
+
+    pub fn matches(&self, todo: &Todo) -> bool {
+        match self {
+            Self::And(left, right) => left.matches(todo) && right.matches(todo),
+            Self::Or(left, right) => left.matches(todo) || right.matches(todo),
+            Self::Not(filter) => !filter.matches(todo),
+            Self::Status(Status::Open) => !todo.complete,
+            Self::Status(Status::Done) => todo.complete,
+            Self::Status(Status::Partial) => todo.is_partial(),
+            Self::Tag(tag) => todo.tags.contains(tag),
+            Self::Context(context) => todo.contexts.contains(context),
+            Self::Priority(priority) => todo.priority == *priority,
+            Self::Date(field, comparison, value) => {
+                let date = match field {
+                    DateField::Due => todo.due,
+                    DateField::Created => todo.created.map(|t| t.date()),
+                    DateField::Completed => todo.completed.map(|t| t.date()),
+                };
+
+                match (date, value) {
+                    (Some(date), Some(value)) => comparison.compare(date, *value),
+                    (None, None) => true,
+                    _ => false,
+                }
+            }
+            Self::Text(text) => {
+                todo.contents.to_lowercase().contains(text)
+                    || todo
+                        .notes
+                        .as_ref()
+                        .is_some_and(|n| n.to_lowercase().contains(text))
+            }
+            Self::Id(id) => todo.id.as_ref() == Some(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FilterTokens {
+    OpenParen,
+    CloseParen,
+    And,
+    Or,
+    Not,
+    /// A condition such as tag:api, split up by the parser
+    Condition(String),
+    EndOfInput,
+}
+
+impl FilterTokens {
+    fn describe(&self) -> String {
+        match self {
+            Self::OpenParen => "(".to_string(),
+            Self::CloseParen => ")".to_string(),
+            Self::And => "and".to_string(),
+            Self::Or => "or".to_string(),
+            Self::Not => "not".to_string(),
+            Self::Condition(condition) => format!("\"{condition}\""),
+            Self::EndOfInput => "end of filter".to_string(),
+        }
+    }
+}
+
+// Each token is paired with the 1 indexed column it begins at
+type Token = (FilterTokens, usize);
+
+fn tokenize_filter(input: &str) -> Result<Vec<Token>, ParseError> {
+    //! Double quotes group a value containing spaces or parentheses, e.g. text:"fix login"
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().enumerate().peekable();
+
+    while let Some((ind, ch)) = chars.next() {
+        let column = ind + 1;
+
+        match ch {
+            _ if ch.is_whitespace() => (),
+            '(' => tokens.push((FilterTokens::OpenParen, column)),
+            ')' => tokens.push((FilterTokens::CloseParen, column)),
+            _ => {
+                let mut word = String::new();
+                let mut quoted = ch == '"';
+
+                if !quoted {
+                    word.push(ch);
+                }
+
+                while let Some(&(_, next)) = chars.peek() {
+                    if !quoted && (next.is_whitespace() || next == '(' || next == ')') {
+                        break;
+                    }
+
+                    chars.next();
+
+                    match next {
+                        '"' => quoted = !quoted,
+                        _ => word.push(next),
+                    }
+                }
+
+                if quoted {
+                    return Err(ParseError {
+                        line: 1,
+                        column: input.chars().count() + 1,
+                        expected: "closing \"".to_string(),
+                        found: "end of filter".to_string(),
+                    });
+                }
+
+                let token = match word.to_ascii_lowercase().as_str() {
+                    "and" => FilterTokens::And,
+                    "or" => FilterTokens::Or,
+                    "not" => FilterTokens::Not,
+                    _ => FilterTokens::Condition(word),
+                };
+
+                tokens.push((token, column));
+            }
+        }
+    }
+
+    tokens.push((FilterTokens::EndOfInput, input.chars().count() + 1));
+
+    Ok(tokens)
+}
+
+fn unexpected(token: &Token, expected: &str) -> ParseError {
+    ParseError {
+        line: 1,
+        column: token.1,
+        expected: expected.to_string(),
+        found: token.0.describe(),
+    }
+}
+
+fn parse_filter_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    //! A date as accepted by date::parse_date, or a number of days or weeks from today such as
+    //! 7d, 2w or -1w.
+    let number = |unit: char| value.strip_suffix(unit)?.parse::<i64>().ok();
+
+    let days = match (number('d'), number('w')) {
+        (Some(days), _) => days,
+        (_, Some(weeks)) => weeks.checked_mul(7)?,
+        _ => return date::parse_date(value, today),
+    };
+
+    match days < 0 {
+        true => today.checked_sub_days(Days::new(days.unsigned_abs())),
+        false => today.checked_add_days(Days::new(days as u64)),
+    }
+}
+
+struct FilterParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    today: NaiveDate,
+}
+
+// Grammar of a filter, parsed top down:
+//   filter    := or EndOfInput
+//   or        := and ( or and )*
+//   and       := not ( and not )*
+//   not       := not not | primary
+//   primary   := ( or ) | Condition
+impl<'a> FilterParser<'a> {
+    fn peek(&self) -> &'a Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> &'a Token {
+        let token = self.peek();
+
+        if token.0 != FilterTokens::EndOfInput {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn parse_filter(&mut self) -> Result<Filter, ParseError> {
+        let filter = self.parse_or()?;
+
+        match self.peek() {
+            (FilterTokens::EndOfInput, _) => Ok(filter),
+            token => Err(unexpected(token, "and, or or end of filter")),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.parse_and()?;
+
+        while self.peek().0 == FilterTokens::Or {
+            self.advance();
+            filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.parse_not()?;
+
+        while self.peek().0 == FilterTokens::And {
+            self.advance();
+            filter = Filter::And(Box::new(filter), Box::new(self.parse_not()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn parse_not(&mut self) -> Result<Filter, ParseError> {
+        match self.peek() {
+            (FilterTokens::Not, _) => {
+                self.advance();
+                Ok(Filter::Not(Box::new(self.parse_not()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter, ParseError> {
+        match self.advance() {
+            (FilterTokens::OpenParen, _) => {
+                let filter = self.parse_or()?;
+
+                match self.advance() {
+                    (FilterTokens::CloseParen, _) => Ok(filter),
+                    token => Err(unexpected(token, ")")),
+                }
+            }
+            token @ (FilterTokens::Condition(condition), _) => {
+                self.parse_condition(condition, token)
+            }
+            token => Err(unexpected(token, "condition, not or (")),
+        }
+    }
+
+    fn parse_condition(&self, condition: &str, token: &Token) -> Result<Filter, ParseError> {
+        let split = match condition.find([':', '<', '>', '=']) {
+            Some(split) => split,
+            None => return Err(unexpected(token, "condition such as tag:api or due<7d")),
+        };
+
+        let (field, rest) = condition.split_at(split);
+
+        let (comparison, value) = match rest {
+            _ if rest.starts_with("<=") => (Comparison::Le, &rest[2..]),
+            _ if rest.starts_with(">=") => (Comparison::Ge, &rest[2..]),
+            _ if rest.starts_with('<') => (Comparison::Lt, &rest[1..]),
+            _ if rest.starts_with('>') => (Comparison::Gt, &rest[1..]),
+            _ => (Comparison::Eq, &rest[1..]),
+        };
+
+        // Only dates can be ordered, everything else is compared with : or =
+        let date_field = match field.to_ascii_lowercase().as_str() {
+            "due" => Some(DateField::Due),
+            "created" => Some(DateField::Created),
+            "done" | "completed" => Some(DateField::Completed),
+            _ if comparison != Comparison::Eq => {
+                return Err(unexpected(token, &format!(": after {field}")));
+            }
+            _ => None,
+        };
+
+        if value.is_empty() {
+            return Err(unexpected(token, &format!("value after {field}")));
+        }
+
+        let filter = match (date_field, field.to_ascii_lowercase().as_str()) {
+            (Some(date_field), _) => match value {
+                "none" if comparison == Comparison::Eq => {
+                    Filter::Date(date_field, comparison, None)
+                }
+                _ => match parse_filter_date(value, self.today) {
+                    Some(date) => Filter::Date(date_field, comparison, Some(date)),
+                    None => return Err(unexpected(token, "date such as 2026-11-01, today or 7d")),
+                },
+            },
+            (None, "status") => match value.to_ascii_lowercase().as_str() {
+                "open" | "todo" => Filter::Status(Status::Open),
+                "done" => Filter::Status(Status::Done),
+                "partial" => Filter::Status(Status::Partial),
+                _ => return Err(unexpected(token, "status (open, done or partial)")),
+            },
+            (None, "tag") => Filter::Tag(value.to_string()),
+            (None, "ctx" | "context") => Filter::Context(value.to_string()),
+            (None, "priority" | "p") => match value {
+                "none" => Filter::Priority(None),
+                _ => match value.parse() {
+                    Ok(priority) => Filter::Priority(Some(priority)),
+                    Err(_) => return Err(unexpected(token, "priority (A, B, C or none)")),
+                },
+            },
+            (None, "text") => Filter::Text(value.to_lowercase()),
+            (None, "id") => Filter::Id(value.trim_start_matches('#').to_string()),
+            _ => {
+                return Err(unexpected(
+                    token,
+                    "field (status, tag, ctx, priority, due, created, done, text or id)",
+                ))
+            }
+        };
+
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn today() -> NaiveDate {
+        date(2026, 10, 18)
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let filter = Filter::parse(
+            "status:open and (tag:api or priority:A) and due<7d",
+            today(),
+        );
+
+        assert_eq!(
+            Ok(Filter::And(
+                Box::new(Filter::And(
+                    Box::new(Filter::Status(Status::Open)),
+                    Box::new(Filter::Or(
+                        Box::new(Filter::Tag("api".to_string())),
+                        Box::new(Filter::Priority(Some(Priority::A)))
+                    ))
+                )),
+                Box::new(Filter::Date(
+                    DateField::Due,
+                    Comparison::Lt,
+                    Some(date(2026, 10, 25))
+                ))
+            )),
+            filter
+        );
+
+        assert_eq!(
+            Ok(Filter::Or(
+                Box::new(Filter::Tag("a".to_string())),
+                Box::new(Filter::And(
+                    Box::new(Filter::Not(Box::new(Filter::Tag("b".to_string())))),
+                    Box::new(Filter::Text("fix login".to_string()))
+                ))
+            )),
+            Filter::parse("tag:a OR not tag:b and text:\"Fix login\"", today())
+        );
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(
+            Ok(Filter::Date(
+                DateField::Completed,
+                Comparison::Ge,
+                Some(date(2026, 10, 11))
+            )),
+            Filter::parse("done>=-1w", today())
+        );
+        assert_eq!(
+            Ok(Filter::Date(DateField::Due, Comparison::Eq, None)),
+            Filter::parse("due:none", today())
+        );
+        assert_eq!(
+            Ok(Filter::Date(
+                DateField::Created,
+                Comparison::Le,
+                Some(date(2026, 10, 1))
+            )),
+            Filter::parse("created<=2026-10-01", today())
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = Filter::parse("status:open and (tag:api", today()).unwrap_err();
+        assert_eq!((25, ")"), (error.column, error.expected.as_str()));
+
+        let error = Filter::parse("status:open tag:api", today()).unwrap_err();
+        assert_eq!((13, "\"tag:api\""), (error.column, error.found.as_str()));
+
+        let error = Filter::parse("owner:me", today()).unwrap_err();
+        assert_eq!(1, error.column);
+
+        assert!(Filter::parse("tag<api", today()).is_err());
+        assert!(Filter::parse("due<soon", today()).is_err());
+        assert!(Filter::parse("status:open and", today()).is_err());
+        assert!(Filter::parse("text:\"open", today()).is_err());
+        assert!(Filter::parse("", today()).is_err());
+    }
+
+    #[test]
+    fn matches_todos() {
+        let mut todo = Todo::from_text(None, "Fix login +api @office");
+        todo.due = Some(date(2026, 10, 20));
+        todo.priority = Some(Priority::B);
+
+        let matches = |input: &str| Filter::parse(input, today()).unwrap().matches(&todo);
+
+        assert!(matches("status:open and tag:api and ctx:office"));
+        assert!(matches("due<7d and due>today and not due:none"));
+        assert!(matches("priority:b or priority:A"));
+        assert!(matches("text:LOGIN"));
+        assert!(!matches("status:done or tag:backend or priority:none"));
+        assert!(!matches("done>=-1w"));
+        assert!(matches("done:none"));
+    }
+}
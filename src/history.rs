@@ -0,0 +1,356 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    date::{self, DISPLAY_TIME_FORMAT, TIME_FORMAT},
+    error::{ParseError, WhatodoError},
+    storage,
+};
+
+type Result<T> = std::result::Result<T, WhatodoError>;
+
+const HISTORY_HEADER: &str = "#whatodo history 1";
+
+/// Changes older than this are forgotten
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A change made to a list, along with the list as it was on the other side of the change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub time: NaiveDateTime,
+    /// The command which made the change, e.g. remove 2-6
+    pub action: String,
+    /// Contents of the list file before the change for entries which can be undone, after the
+    /// change for entries which can be redone
+    pub snapshot: String,
+    /// Contents of the archive on the same side of the change, for changes which moved todos to
+    /// or from it
+    pub archive: Option<String>,
+}
+
+/// Changes which can be undone and redone, each kept oldest first. The history of todo.todos is
+/// kept next to it in todo.todos.history, in the format:
+///
+/// #whatodo history 1
+/// @undo 2026-10-18T09:12:44 add Write docs
+/// #whatodo 2
+/// 0|Something|
+/// @redo 2026-10-18T09:13:02 archive
+/// #whatodo 2
+/// 1|Done|
+/// @archive
+/// #whatodo 2
+///
+/// Every snapshot is a list file as written by whatodo, whose lines never start with @.
+#[derive(Debug, Default, PartialEq)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+}
+
+pub fn history_path(path: &Path) -> PathBuf {
+    //! Path of the history of the list at path, e.g. todo.todos.history
+    let mut history = path.as_os_str().to_owned();
+    history.push(".history");
+
+    PathBuf::from(history)
+}
+
+impl History {
+    pub fn load(path: &Path) -> Result<Self> {
+        //! Reads the history at path, a missing file being an empty history.
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents).map_err(WhatodoError::InvalidHistory),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(WhatodoError::CannotLoadHistory(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        storage::write_atomic(path, &self.to_string(), false)
+            .map_err(WhatodoError::CannotSaveHistory)
+    }
+
+    fn parse(contents: &str) -> std::result::Result<Self, ParseError> {
+        let mut history = Self::default();
+        let mut lines = contents.lines().enumerate();
+
+        let error = |line: usize, expected: &str, found: &str| ParseError {
+            line: line + 1,
+            column: 1,
+            expected: expected.to_string(),
+            found: found.to_string(),
+        };
+
+        match lines.next() {
+            Some((_, HISTORY_HEADER)) | None => (),
+            Some((line, found)) => return Err(error(line, HISTORY_HEADER, found)),
+        }
+
+        for (line, text) in lines {
+            let Some(entry) = text.strip_prefix('@') else {
+                // Every line up to the next entry belongs to the snapshot of the last one, or to
+                // its archive once it has one
+                let last = history
+                    .redo
+                    .last_mut()
+                    .or(history.undo.last_mut())
+                    .ok_or_else(|| error(line, "@undo or @redo", text))?;
+
+                let snapshot = last.archive.as_mut().unwrap_or(&mut last.snapshot);
+                snapshot.push_str(text);
+                snapshot.push('\n');
+
+                continue;
+            };
+
+            if entry == "archive" {
+                match history.redo.last_mut().or(history.undo.last_mut()) {
+                    Some(last) if last.archive.is_none() => last.archive = Some(String::new()),
+                    _ => return Err(error(line, "@undo or @redo", text)),
+                }
+
+                continue;
+            }
+
+            let mut fields = entry.splitn(3, ' ');
+
+            let (kind, time, action) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(kind), Some(time), action) => (kind, time, action.unwrap_or_default()),
+                _ => return Err(error(line, "@undo or @redo with a time", text)),
+            };
+
+            let entry = Entry {
+                time: NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+                    .map_err(|_| error(line, "YYYY-MM-DDTHH:MM:SS", time))?,
+                action: action.to_string(),
+                snapshot: String::new(),
+                archive: None,
+            };
+
+            match kind {
+                "undo" if history.redo.is_empty() => history.undo.push(entry),
+                "redo" => history.redo.push(entry),
+                _ => return Err(error(line, "@undo or @redo", text)),
+            }
+        }
+
+        Ok(history)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+
+    pub fn record(&mut self, action: &str, before: String, archive: Option<String>) {
+        //! Records a change made by action to the list, which was before it, along with the
+        //! archive before it when the change moved todos to or from it. Anything undone can no
+        //! longer be redone once something else changes.
+        self.undo.push(Entry {
+            time: date::now(),
+            // Each entry takes up a single line
+            action: action.replace(['\n', '\r'], " "),
+            snapshot: before,
+            archive,
+        });
+        self.redo.clear();
+
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.drain(..self.undo.len() - HISTORY_LIMIT);
+        }
+    }
+
+    pub fn undo(&mut self, current: String, current_archive: String) -> Option<Entry> {
+        //! Steps back over the latest change, returning it along with the list as it was before
+        //! it. The list and archive as they are now are kept so the change can be redone.
+        let entry = self.undo.pop()?;
+
+        self.redo.push(Entry {
+            snapshot: current,
+            archive: entry.archive.as_ref().map(|_| current_archive),
+            ..entry.clone()
+        });
+
+        Some(entry)
+    }
+
+    pub fn redo(&mut self, current: String, current_archive: String) -> Option<Entry> {
+        //! Makes the latest undone change again, returning it along with the list as it was after
+        //! it. The list and archive as they are now are kept so the change can be undone again.
+        let entry = self.redo.pop()?;
+
+        self.undo.push(Entry {
+            snapshot: current,
+            archive: entry.archive.as_ref().map(|_| current_archive),
+            ..entry.clone()
+        });
+
+        Some(entry)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Entry, bool)> {
+        //! Every change, newest first, along with whether it has been undone
+        self.redo
+            .iter()
+            .map(|e| (e, true))
+            .chain(self.undo.iter().rev().map(|e| (e, false)))
+    }
+}
+
+impl std::fmt::Display for History {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{HISTORY_HEADER}")?;
+
+        let undo = self.undo.iter().map(|e| ("undo", e));
+        let redo = self.redo.iter().map(|e| ("redo", e));
+
+        for (kind, entry) in undo.chain(redo) {
+            writeln!(
+                f,
+                "@{kind} {} {}",
+                entry.time.format(TIME_FORMAT),
+                entry.action
+            )?;
+            write!(f, "{}", entry.snapshot)?;
+
+            if let Some(archive) = &entry.archive {
+                write!(f, "@archive\n{archive}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn render(history: &History) -> String {
+    //! One line per change, newest first, marking those which have been undone
+    let lines: Vec<String> = history
+        .entries()
+        .map(|(entry, undone)| {
+            format!(
+                "{}  {}{}",
+                entry.time.format(DISPLAY_TIME_FORMAT),
+                entry.action,
+                if undone { " (undone)" } else { "" }
+            )
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(contents: &str) -> String {
+        format!("#whatodo 2\n{contents}")
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::default();
+        let archive = snapshot("");
+
+        history.record("add First", snapshot(""), None);
+        history.record("add Second", snapshot("0|First|\n"), None);
+
+        let current = snapshot("0|First|\n0|Second|\n");
+
+        let undone = history.undo(current.clone(), archive.clone()).unwrap();
+        assert_eq!("add Second", undone.action);
+        assert_eq!(snapshot("0|First|\n"), undone.snapshot);
+
+        let undone = history.undo(undone.snapshot, archive.clone()).unwrap();
+        assert_eq!(snapshot(""), undone.snapshot);
+        assert!(history
+            .undo(undone.snapshot.clone(), archive.clone())
+            .is_none());
+
+        assert_eq!(
+            vec![("add Second", true), ("add First", true)],
+            history
+                .entries()
+                .map(|(e, undone)| (e.action.as_str(), undone))
+                .collect::<Vec<_>>()
+        );
+
+        let redone = history.redo(undone.snapshot, archive.clone()).unwrap();
+        assert_eq!("add First", redone.action);
+        assert_eq!(snapshot("0|First|\n"), redone.snapshot);
+
+        let redone = history.redo(redone.snapshot, archive.clone()).unwrap();
+        assert_eq!(current, redone.snapshot);
+        assert_eq!(None, redone.archive);
+        assert!(history.redo(current, archive).is_none());
+    }
+
+    #[test]
+    fn undo_and_redo_archive() {
+        let mut history = History::default();
+
+        history.record("archive", snapshot("1|Done|\n"), Some(snapshot("")));
+
+        let undone = history.undo(snapshot(""), snapshot("1|Done|\n")).unwrap();
+        assert_eq!(Some(snapshot("")), undone.archive);
+
+        let redone = history.redo(snapshot("1|Done|\n"), snapshot("")).unwrap();
+        assert_eq!(snapshot(""), redone.snapshot);
+        assert_eq!(Some(snapshot("1|Done|\n")), redone.archive);
+    }
+
+    #[test]
+    fn record_forgets_undone_changes() {
+        let mut history = History::default();
+
+        history.record("add First", snapshot(""), None);
+        history.undo(snapshot("0|First|\n"), snapshot("")).unwrap();
+        history.record("add Other", snapshot(""), None);
+
+        assert!(history.redo(snapshot("0|Other|\n"), snapshot("")).is_none());
+
+        for ind in 0..HISTORY_LIMIT + 5 {
+            history.record(&format!("add {ind}"), snapshot(""), None);
+        }
+
+        assert_eq!(HISTORY_LIMIT, history.entries().count());
+        assert_eq!("add 5", history.entries().last().unwrap().0.action);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = history_path(&dir.path().join("todo.todos"));
+
+        assert_eq!(History::default(), History::load(&path).unwrap());
+
+        let mut history = History::default();
+        history.record("add First", snapshot(""), None);
+        history.record(
+            "edit 1 'Multi\nline'",
+            snapshot("0|First|\n0|Sub|[0|Deeper|]\n"),
+            None,
+        );
+        history.record(
+            "archive",
+            snapshot("1|Multi\\nline|\n"),
+            Some(snapshot("1|Older|\n")),
+        );
+        history.undo(snapshot(""), snapshot("1|Older|\n1|Multi\\nline|\n"));
+        history.save(&path).unwrap();
+
+        assert!(path.ends_with("todo.todos.history"));
+        assert_eq!(history, History::load(&path).unwrap());
+
+        fs::write(&path, "0|Not a history|\n").unwrap();
+        assert!(matches!(
+            History::load(&path),
+            Err(WhatodoError::InvalidHistory(_))
+        ));
+    }
+}
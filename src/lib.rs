@@ -1,3 +1,12 @@
+pub mod date;
+pub mod editor;
 pub mod error;
+pub mod filter;
+pub mod history;
+pub mod list;
+pub mod path;
+pub mod search;
+pub mod storage;
 pub mod todo;
 pub mod utils;
+pub mod view;
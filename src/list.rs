@@ -0,0 +1,1377 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+
+use crate::{
+    error::WhatodoError,
+    filter::Filter,
+    history::{self, Entry, History},
+    path::{Selection, TodoPath},
+    storage,
+    todo::{self, Priority, Todo},
+    utils,
+};
+
+type Result<T> = std::result::Result<T, WhatodoError>;
+
+/// How completing a todo affects the todos around it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompletionPolicy {
+    /// Completing a todo also completes every todo below it
+    pub cascade: bool,
+    /// A parent is completed once all of its sub todos are, and reopened when one of them is
+    pub auto_complete_parents: bool,
+}
+
+/// A todo list together with the file it is stored in.
+///
+/// Every index path used by the methods below is 0 indexed, the first element selects a top
+/// level todo and each following element selects a sub todo of the previous one.
+#[derive(Debug)]
+pub struct TodoList {
+    path: PathBuf,
+    pub todos: Vec<Todo>,
+    /// Keep a copy of the previous version of the file on save
+    pub backup: bool,
+    pub policy: CompletionPolicy,
+    /// The command changing the list, recorded in its history when saved. Saves without an
+    /// action are not recorded.
+    pub action: Option<String>,
+    /// The list as it was last loaded or saved, to be restored by undo
+    saved: String,
+    /// The archive as it was before todos were moved to or from it, to be restored along with
+    /// the list by undo
+    archived: Option<String>,
+}
+
+impl TodoList {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            todos: Vec::new(),
+            backup: false,
+            policy: CompletionPolicy::default(),
+            action: None,
+            saved: todo::to_todos_file_string(&[]),
+            archived: None,
+        }
+    }
+
+    pub fn init(path: PathBuf) -> Result<Self> {
+        storage::init_todos(&path)?;
+
+        Ok(Self::new(path))
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let todos = storage::load_todos(&path)?;
+
+        let mut todos_list = Self {
+            todos,
+            ..Self::new(path)
+        };
+
+        todos_list.assign_missing_ids();
+        todos_list.saved = todo::to_todos_file_string(&todos_list.todos);
+
+        Ok(todos_list)
+    }
+
+    fn ids(&self) -> HashSet<String> {
+        fn collect(todos: &[Todo], ids: &mut HashSet<String>) {
+            for todo in todos {
+                ids.extend(todo.id.clone());
+                collect(&todo.sub_todos, ids);
+            }
+        }
+
+        let mut ids = HashSet::new();
+        collect(&self.todos, &mut ids);
+
+        ids
+    }
+
+    fn assign_missing_ids(&mut self) {
+        //! Gives an id to every todo loaded without one, e.g. from lists written before ids
+        //! existed. The ids are derived from where each todo is and what it says, so they are
+        //! the same every time the list is loaded until it is saved with them.
+        fn assign(todos: &mut [Todo], parent: &[usize], ids: &mut HashSet<String>) {
+            for (ind, todo) in todos.iter_mut().enumerate() {
+                let mut path = parent.to_vec();
+                path.push(ind);
+
+                if todo.id.is_none() {
+                    // Paths never contain a bar, so the path and contents can't run into each other
+                    let seed = format!("{}|{}", TodoPath::from_indices(&path), todo.contents);
+
+                    let id = utils::generate_id(utils::stable_hash(seed.as_bytes()), ids);
+                    ids.insert(id.clone());
+                    todo.id = Some(id);
+                }
+
+                assign(&mut todo.sub_todos, &path, ids);
+            }
+        }
+
+        let mut ids = self.ids();
+        assign(&mut self.todos, &[], &mut ids);
+    }
+
+    pub fn resolve(&self, path: &TodoPath) -> Result<Vec<usize>> {
+        //! The 0 indexed path of a path given by a user, see TodoPath::resolve
+        path.resolve(&self.todos)
+            .ok_or(WhatodoError::IndexOutOfBounds)
+    }
+
+    pub fn select(&self, selection: &Selection) -> Result<Vec<Vec<usize>>> {
+        //! The 0 indexed paths of every todo selected by a user, see Selection::resolve
+        selection
+            .resolve(&self.todos)
+            .ok_or(WhatodoError::IndexOutOfBounds)
+    }
+
+    pub fn find_id(&self, id: &str) -> Option<Vec<usize>> {
+        utils::find_id(&self.todos, id)
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        //! Writes the list to its file, recording the change in its history when there is an
+        //! action and the list has changed.
+        let contents = todo::to_todos_file_string(&self.todos);
+
+        let action = match self.action.as_deref() {
+            Some(action) if contents != self.saved => action.to_string(),
+            _ => return self.write(),
+        };
+
+        let history_path = history::history_path(&self.path);
+        let mut history = History::load(&history_path)?;
+        let before = self.saved.clone();
+
+        // The change is only recorded once it has been made, so it can always be undone
+        self.write()?;
+
+        history.record(&action, before, self.archived.take());
+        history.save(&history_path)
+    }
+
+    fn write(&mut self) -> Result<()> {
+        storage::save_todos(&self.path, &self.todos, self.backup)?;
+
+        self.saved = todo::to_todos_file_string(&self.todos);
+
+        Ok(())
+    }
+
+    pub fn load_archive(&self) -> Result<TodoList> {
+        //! The list completed todos of this list are archived to, empty when nothing has been
+        //! archived yet.
+        let path = storage::archive_path(&self.path);
+
+        match path.exists() {
+            true => TodoList::load(path),
+            false => Ok(TodoList::new(path)),
+        }
+    }
+
+    pub fn history(&self) -> Result<History> {
+        History::load(&history::history_path(&self.path))
+    }
+
+    pub fn undo(&mut self) -> Result<Entry> {
+        //! Restores the list as it was before its latest change, returning the change undone.
+        self.step(History::undo)?.ok_or(WhatodoError::NothingToUndo)
+    }
+
+    pub fn redo(&mut self) -> Result<Entry> {
+        //! Makes the latest undone change again, returning the change redone.
+        self.step(History::redo)?.ok_or(WhatodoError::NothingToRedo)
+    }
+
+    fn step<F>(&mut self, step: F) -> Result<Option<Entry>>
+    where
+        F: FnOnce(&mut History, String, String) -> Option<Entry>,
+    {
+        let history_path = history::history_path(&self.path);
+        let mut history = History::load(&history_path)?;
+        let mut archive = self.load_archive()?;
+
+        let current = todo::to_todos_file_string(&self.todos);
+        let current_archive = todo::to_todos_file_string(&archive.todos);
+
+        let entry = match step(&mut history, current, current_archive) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        self.todos = todo::from_todos_file_string(&entry.snapshot)
+            .map_err(WhatodoError::CannotParseTodos)?;
+
+        if let Some(snapshot) = &entry.archive {
+            archive.todos =
+                todo::from_todos_file_string(snapshot).map_err(WhatodoError::CannotParseTodos)?;
+            archive.write()?;
+        }
+
+        self.write()?;
+        history.save(&history_path)?;
+
+        Ok(Some(entry))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.todos.is_empty()
+    }
+
+    pub fn get(&self, item: &[usize]) -> Option<&Todo> {
+        let (final_index, parents) = item.split_last()?;
+
+        let mut curr_root = &self.todos;
+
+        for ind in parents {
+            curr_root = &curr_root.get(*ind)?.sub_todos;
+        }
+
+        curr_root.get(*final_index)
+    }
+
+    pub fn get_mut(&mut self, item: &[usize]) -> Option<&mut Todo> {
+        if item.is_empty() {
+            return None;
+        }
+
+        utils::get_mut_from_num_depth(&mut self.todos, item)
+    }
+
+    fn sub_todos_mut(&mut self, parent: &[usize]) -> Result<&mut Vec<Todo>> {
+        match parent.is_empty() {
+            true => Ok(&mut self.todos),
+            false => match self.get_mut(parent) {
+                Some(todo) => Ok(&mut todo.sub_todos),
+                None => Err(WhatodoError::IndexOutOfBounds),
+            },
+        }
+    }
+
+    // There can exist multiple sub todos that are the same, but no base level todos may be the same
+    pub fn add(&mut self, parent: &[usize], contents: String) -> Result<()> {
+        self.add_todo(parent, Todo::new(None, contents))
+    }
+
+    pub fn add_todo(&mut self, parent: &[usize], mut todo: Todo) -> Result<()> {
+        if todo.id.is_none() {
+            todo.id = Some(utils::generate_id(utils::random_seed(), &self.ids()));
+        }
+
+        let curr_root = self.sub_todos_mut(parent)?;
+
+        if utils::search_all_todos_content(curr_root, &todo.contents) {
+            return Err(WhatodoError::TodoAlreadyInList);
+        }
+
+        curr_root.push(todo);
+
+        Ok(())
+    }
+
+    pub fn edit(&mut self, item: &[usize], text: String) -> Result<()> {
+        //! Changes the contents of the item in place, keeping its position and sub todos. Tags
+        //! and contexts in text are added to the item's own, as they are when adding a todo. The
+        //! same duplicate rule as add applies, ignoring the item's own current contents.
+        let Todo {
+            contents,
+            tags,
+            contexts,
+            ..
+        } = Todo::from_text(None, &text);
+
+        if contents.is_empty() {
+            return Err(WhatodoError::EmptyTodo);
+        }
+
+        let (index_to_edit, parent) = match item.split_last() {
+            Some((last, parent)) => (*last, parent),
+            None => return Err(WhatodoError::IndexOutOfBounds),
+        };
+
+        let curr_root = self.sub_todos_mut(parent)?;
+
+        if index_to_edit >= curr_root.len() {
+            return Err(WhatodoError::IndexOutOfBounds);
+        }
+
+        let is_duplicate = curr_root.iter().enumerate().any(|(ind, todo)| {
+            (ind != index_to_edit && todo.contents == contents)
+                || utils::search_all_todos_content(&todo.sub_todos, &contents)
+        });
+
+        if is_duplicate {
+            return Err(WhatodoError::TodoAlreadyInList);
+        }
+
+        let todo = &mut curr_root[index_to_edit];
+
+        todo.contents = contents;
+        todo.tags.extend(tags);
+        todo.contexts.extend(contexts);
+
+        Ok(())
+    }
+
+    pub fn prioritize(&mut self, item: &[usize], priority: Option<Priority>) -> Result<()> {
+        match self.get_mut(item) {
+            Some(todo) => {
+                todo.priority = priority;
+                Ok(())
+            }
+            None => Err(WhatodoError::IndexOutOfBounds),
+        }
+    }
+
+    pub fn set_due(&mut self, item: &[usize], due: Option<NaiveDate>) -> Result<()> {
+        match self.get_mut(item) {
+            Some(todo) => {
+                todo.due = due;
+                Ok(())
+            }
+            None => Err(WhatodoError::IndexOutOfBounds),
+        }
+    }
+
+    pub fn set_notes(&mut self, item: &[usize], notes: Option<String>) -> Result<()> {
+        match self.get_mut(item) {
+            Some(todo) => {
+                todo.notes = notes;
+                Ok(())
+            }
+            None => Err(WhatodoError::IndexOutOfBounds),
+        }
+    }
+
+    pub fn complete(&mut self, item: &[usize]) -> Result<()> {
+        self.set_complete(item, true)
+    }
+
+    pub fn uncomplete(&mut self, item: &[usize]) -> Result<()> {
+        self.set_complete(item, false)
+    }
+
+    pub fn set_complete(&mut self, item: &[usize], complete: bool) -> Result<()> {
+        let policy = self.policy;
+
+        match self.get_mut(item) {
+            Some(todo) if complete && policy.cascade => todo.set_complete_recursive(true),
+            Some(todo) => todo.mark_complete(complete),
+            None => return Err(WhatodoError::IndexOutOfBounds),
+        }
+
+        if policy.auto_complete_parents {
+            self.update_parents(item, complete);
+        }
+
+        Ok(())
+    }
+
+    fn update_parents(&mut self, item: &[usize], complete: bool) {
+        for depth in (1..item.len()).rev() {
+            let parent = match self.get_mut(&item[..depth]) {
+                Some(parent) => parent,
+                None => return,
+            };
+
+            match complete {
+                true if parent.all_sub_todos_complete() => parent.mark_complete(true),
+                // Nothing above can change if this parent stays open
+                true => return,
+                false => parent.mark_complete(false),
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, item: &[usize]) -> Result<bool> {
+        //! Flips the completion of the item, returning whether it is now complete.
+        let complete = match self.get(item) {
+            Some(todo) => !todo.complete,
+            None => return Err(WhatodoError::IndexOutOfBounds),
+        };
+
+        self.set_complete(item, complete)?;
+
+        Ok(complete)
+    }
+
+    pub fn remove(&mut self, item: &[usize]) -> Result<Todo> {
+        let (index_to_remove, parent) = match item.split_last() {
+            Some((last, parent)) => (*last, parent),
+            None => return Err(WhatodoError::IndexOutOfBounds),
+        };
+
+        let curr_root = self.sub_todos_mut(parent)?;
+
+        match index_to_remove < curr_root.len() {
+            true => Ok(curr_root.remove(index_to_remove)),
+            false => Err(WhatodoError::IndexOutOfBounds),
+        }
+    }
+
+    pub fn move_todo(&mut self, from: &[usize], to: &[usize]) -> Result<()> {
+        //! Moves the item at from, along with all of its sub todos, so that it ends up at the
+        //! path to. The path to refers to the list as it is before the move, so moving an item
+        //! out from before one of the destination's parents is accounted for.
+        if from.is_empty() || to.is_empty() || to.starts_with(from) && to.len() > from.len() {
+            // A todo cannot be moved beneath itself
+            return Err(WhatodoError::IndexOutOfBounds);
+        }
+
+        let mut to = to.to_vec();
+        let depth = from.len() - 1;
+
+        // Removing from shifts the later siblings it leaves behind up by one
+        if to.len() > from.len() && to[..depth] == from[..depth] && to[depth] > from[depth] {
+            to[depth] -= 1;
+        }
+
+        let todo = self.remove(from)?;
+
+        let (insert_at, parent) = to.split_last().expect("to is not empty");
+
+        let result = match self.sub_todos_mut(parent) {
+            Ok(curr_root) if *insert_at > curr_root.len() => Err(WhatodoError::IndexOutOfBounds),
+            Ok(curr_root) if utils::search_all_todos_content(curr_root, &todo.contents) => {
+                Err(WhatodoError::TodoAlreadyInList)
+            }
+            Ok(curr_root) => {
+                curr_root.insert(*insert_at, todo);
+                return Ok(());
+            }
+            Err(e) => Err(e),
+        };
+
+        // Put the todo back where it came from so a failed move changes nothing
+        let (index, parent) = from.split_last().expect("from is not empty");
+        self.sub_todos_mut(parent)
+            .expect("parent of a removed todo exists")
+            .insert(*index, todo);
+
+        result
+    }
+
+    pub fn sibling_count(&self, item: &[usize]) -> Option<usize> {
+        //! Number of todos in the list the item resides in, the item included
+        let (_, parent) = item.split_last()?;
+
+        match parent.is_empty() {
+            true => Some(self.todos.len()),
+            false => self.get(parent).map(|todo| todo.sub_todos.len()),
+        }
+    }
+
+    fn prune<F: Fn(&Todo) -> bool>(todos: &mut Vec<Todo>, remove: &F) -> usize {
+        //! Removes the todos for which remove returns true at any depth, returning how many were
+        //! removed. A todo with sub todos left after pruning them is kept, so nothing of the
+        //! other kind is lost with it.
+        let mut removed = 0;
+
+        for todo in todos.iter_mut() {
+            removed += Self::prune(&mut todo.sub_todos, remove);
+        }
+
+        let before = todos.len();
+        todos.retain(|t| !remove(t) || !t.sub_todos.is_empty());
+
+        removed + before - todos.len()
+    }
+
+    pub fn remove_done(&mut self) {
+        //! Removes completed todos throughout the tree
+        Self::prune(&mut self.todos, &|t| t.complete);
+    }
+
+    pub fn remove_todo(&mut self) {
+        //! Removes todos not completed throughout the tree
+        Self::prune(&mut self.todos, &|t| !t.complete);
+    }
+
+    pub fn archive_done(&mut self, archive: &mut TodoList) -> usize {
+        //! Moves the todos remove_done would remove, along with their sub todos, to archive and
+        //! returns how many were moved. Todos archived from under an open todo are kept under a
+        //! copy of it in archive, which later archived todos from under it are added to.
+        fn take_done(todos: &mut Vec<Todo>) -> (Vec<Todo>, usize) {
+            let mut archived = Vec::new();
+            let mut count = 0;
+
+            for mut todo in std::mem::take(todos) {
+                if todo.is_done() {
+                    archived.push(todo);
+                    count += 1;
+
+                    continue;
+                }
+
+                let (done, done_count) = take_done(&mut todo.sub_todos);
+
+                if !done.is_empty() {
+                    archived.push(Todo {
+                        sub_todos: done,
+                        ..detached(&todo)
+                    });
+                    count += done_count;
+                }
+
+                todos.push(todo);
+            }
+
+            (archived, count)
+        }
+
+        let (archived, count) = take_done(&mut self.todos);
+
+        self.archived = Some(archive.saved.clone());
+
+        // The todos being archived are newer than any copies of them already there
+        merge(&mut archive.todos, archived, true);
+
+        count
+    }
+
+    pub fn unarchive(&mut self, archive: &mut TodoList, id: &str) -> Result<()> {
+        //! Moves the todo with id, along with its sub todos, from archive back to this list. It is
+        //! put back under the todos it was archived from under, wherever they are in this list
+        //! now, which are added again from archive when they are no longer in this list.
+        let item = archive
+            .find_id(id)
+            .ok_or_else(|| WhatodoError::NoSuchId(id.to_string()))?;
+
+        // The todo followed by the todos it was archived from under, innermost first
+        let chain = (1..=item.len())
+            .rev()
+            .map(|depth| archive.get(&item[..depth]).map(detached))
+            .collect::<Option<Vec<Todo>>>()
+            .ok_or(WhatodoError::IndexOutOfBounds)?;
+
+        // The innermost of them still in this list, which the rest are put back under
+        let found = chain.iter().enumerate().find_map(|(depth, todo)| {
+            let path = utils::find_id(&self.todos, todo.id.as_deref()?)?;
+            Some((depth, path))
+        });
+
+        let (parent, copies) = match found {
+            Some((depth, path)) => (path, depth),
+            None => (Vec::new(), chain.len()),
+        };
+
+        // A copy merged with the todo of the same id is not a duplicate
+        if let Some(top) = copies.checked_sub(1).map(|depth| &chain[depth]) {
+            let curr_root = self.sub_todos_mut(&parent)?;
+
+            if utils::search_all_todos_content(curr_root, &top.contents) {
+                return Err(WhatodoError::TodoAlreadyInList);
+            }
+        }
+
+        self.archived = Some(archive.saved.clone());
+
+        let mut restored = archive.remove(&item)?;
+
+        let todos = match copies {
+            0 => std::mem::take(&mut restored.sub_todos),
+            _ => {
+                for copy in chain[1..copies].iter().cloned() {
+                    restored = Todo {
+                        sub_todos: vec![restored],
+                        ..copy
+                    };
+                }
+
+                vec![restored]
+            }
+        };
+
+        // Copies in archive were only kept to hold what was archived under them, and are now
+        // either in this list or copied back to it
+        for depth in (1..item.len()).rev() {
+            match archive.get(&item[..depth]) {
+                Some(parent) if parent.sub_todos.is_empty() => {
+                    archive.remove(&item[..depth])?;
+                }
+                _ => break,
+            }
+        }
+
+        merge(self.sub_todos_mut(&parent)?, todos, false);
+
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.todos.clear();
+    }
+
+    pub fn matching(&self, filter: &Filter) -> Vec<Vec<usize>> {
+        //! Paths of every todo, at any depth, matched by filter, parents before their sub todos
+        fn collect(todos: &[Todo], filter: &Filter, parent: &[usize], paths: &mut Vec<Vec<usize>>) {
+            for (ind, todo) in todos.iter().enumerate() {
+                let mut path = parent.to_vec();
+                path.push(ind);
+
+                if filter.matches(todo) {
+                    paths.push(path.clone());
+                }
+
+                collect(&todo.sub_todos, filter, &path, paths);
+            }
+        }
+
+        let mut paths = Vec::new();
+        collect(&self.todos, filter, &[], &mut paths);
+
+        paths
+    }
+
+    pub fn remove_where(&mut self, filter: &Filter) -> usize {
+        //! Removes every todo matched by filter at any depth, returning how many were removed. As
+        //! with remove_done, a matched todo still holding todos which don't match is kept.
+        Self::prune(&mut self.todos, &|t| filter.matches(t))
+    }
+
+    pub fn complete_where(&mut self, filter: &Filter) -> Result<usize> {
+        //! Completes every todo matched by filter, following the completion policy, returning how
+        //! many todos matched.
+        self.complete_all(&self.matching(filter))
+    }
+
+    pub fn complete_all(&mut self, items: &[Vec<usize>]) -> Result<usize> {
+        //! Completes every item, following the completion policy, returning how many were given.
+        //! Nothing is completed unless every item exists.
+        if items.iter().any(|item| self.get(item).is_none()) {
+            return Err(WhatodoError::IndexOutOfBounds);
+        }
+
+        for item in items {
+            self.complete(item)?;
+        }
+
+        Ok(items.len())
+    }
+
+    pub fn remove_all(&mut self, items: &[Vec<usize>]) -> Result<usize> {
+        //! Removes every item along with its sub todos, returning how many were removed. Every path
+        //! refers to the list as it is before any removal, and nothing is removed unless every
+        //! item exists.
+        if items.iter().any(|item| self.get(item).is_none()) {
+            return Err(WhatodoError::IndexOutOfBounds);
+        }
+
+        // Items below another item go with it, the rest are removed last first so removing one
+        // never shifts the path of another
+        let mut items: Vec<&Vec<usize>> = items
+            .iter()
+            .filter(|item| {
+                !items
+                    .iter()
+                    .any(|other| other.len() < item.len() && item.starts_with(other))
+            })
+            .collect();
+        items.sort_unstable_by(|a, b| b.cmp(a));
+        items.dedup();
+
+        for item in items.iter() {
+            self.remove(item)?;
+        }
+
+        Ok(items.len())
+    }
+
+    pub fn done(&self) -> impl Iterator<Item = &Todo> {
+        self.todos.iter().filter(|t| t.complete)
+    }
+
+    pub fn open(&self) -> impl Iterator<Item = &Todo> {
+        self.todos.iter().filter(|t| !t.complete)
+    }
+}
+
+fn detached(todo: &Todo) -> Todo {
+    //! A copy of todo without its sub todos
+    Todo {
+        sub_todos: Vec::new(),
+        ..todo.clone()
+    }
+}
+
+fn merge(into: &mut Vec<Todo>, todos: Vec<Todo>, replace: bool) {
+    //! Adds todos to into. A todo with the id of one already in into is merged with it instead,
+    //! along with its sub todos, taking its fields from todos when replace is set.
+    for mut todo in todos {
+        let existing = match &todo.id {
+            Some(id) => into.iter_mut().find(|t| t.id.as_ref() == Some(id)),
+            None => None,
+        };
+
+        let existing = match existing {
+            Some(existing) => existing,
+            None => {
+                into.push(todo);
+                continue;
+            }
+        };
+
+        let sub_todos = std::mem::take(&mut todo.sub_todos);
+
+        if replace {
+            *existing = Todo {
+                sub_todos: std::mem::take(&mut existing.sub_todos),
+                ..todo
+            };
+        }
+
+        merge(&mut existing.sub_todos, sub_todos, replace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(todos: &[Todo]) -> Vec<&str> {
+        todos.iter().map(|t| t.contents.as_str()).collect()
+    }
+
+    fn example_list() -> TodoList {
+        let mut list = TodoList::new(PathBuf::from("todo.todos"));
+
+        list.add(&[], "First".to_string()).unwrap();
+        list.add(&[], "Second".to_string()).unwrap();
+        list.add(&[0], "Sub".to_string()).unwrap();
+
+        list
+    }
+
+    #[test]
+    fn add_to_nested_list() {
+        let mut list = example_list();
+
+        list.add(&[0, 0], "Sub sub".to_string()).unwrap();
+
+        assert_eq!("Sub sub", list.get(&[0, 0, 0]).unwrap().contents);
+        assert!(matches!(
+            list.add(&[5], "Nowhere".to_string()),
+            Err(WhatodoError::IndexOutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn add_duplicate() {
+        let mut list = example_list();
+
+        assert!(matches!(
+            list.add(&[], "Sub".to_string()),
+            Err(WhatodoError::TodoAlreadyInList)
+        ));
+        assert!(list.add(&[1], "Sub".to_string()).is_ok());
+    }
+
+    #[test]
+    fn complete_and_remove() {
+        let mut list = example_list();
+
+        list.complete(&[0, 0]).unwrap();
+        list.complete(&[1]).unwrap();
+
+        assert!(list.get(&[0, 0]).unwrap().complete);
+        assert_eq!(
+            vec!["Second"],
+            list.done().map(|t| t.contents.as_str()).collect::<Vec<_>>()
+        );
+
+        assert_eq!("Sub", list.remove(&[0, 0]).unwrap().contents);
+        assert!(list.remove(&[0, 0]).is_err());
+
+        list.remove_done();
+
+        assert_eq!(1, list.todos.len());
+    }
+
+    #[test]
+    fn get_mut_out_of_bounds() {
+        let mut list = example_list();
+
+        assert_eq!("Sub", list.get_mut(&[0, 0]).unwrap().contents);
+        assert!(list.get_mut(&[]).is_none());
+        assert!(list.get_mut(&[4, 0]).is_none());
+        assert!(list.get_mut(&[0, 1]).is_none());
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut list = example_list();
+
+        list.edit(&[0], "Renamed".to_string()).unwrap();
+
+        assert_eq!("Renamed", list.todos[0].contents);
+        assert_eq!("Sub", list.todos[0].sub_todos[0].contents);
+
+        // Keeping the same text is not a duplicate
+        assert!(list.edit(&[0], "Renamed".to_string()).is_ok());
+        assert!(matches!(
+            list.edit(&[0], "Second".to_string()),
+            Err(WhatodoError::TodoAlreadyInList)
+        ));
+        assert!(matches!(
+            list.edit(&[1], "Sub".to_string()),
+            Err(WhatodoError::TodoAlreadyInList)
+        ));
+        assert!(list.edit(&[0, 0], "Second".to_string()).is_ok());
+        assert!(list.edit(&[0, 4], "Nowhere".to_string()).is_err());
+    }
+
+    #[test]
+    fn edit_adds_tags_and_contexts() {
+        let mut list = example_list();
+        list.todos[0].tags.insert("api".to_string());
+
+        list.edit(&[0], "Fix +web login @office".to_string())
+            .unwrap();
+
+        let todo = &list.todos[0];
+        assert_eq!("Fix login", todo.contents);
+        assert_eq!(vec!["api", "web"], todo.tags.iter().collect::<Vec<_>>());
+        assert_eq!(vec!["office"], todo.contexts.iter().collect::<Vec<_>>());
+
+        assert!(matches!(
+            list.edit(&[0], "+web".to_string()),
+            Err(WhatodoError::EmptyTodo)
+        ));
+    }
+
+    #[test]
+    fn move_within_list() {
+        let mut list = example_list();
+        list.add(&[], "Third".to_string()).unwrap();
+
+        list.move_todo(&[2], &[0]).unwrap();
+
+        assert_eq!(
+            vec!["Third", "First", "Second"],
+            list.todos
+                .iter()
+                .map(|t| t.contents.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        list.move_todo(&[0], &[2]).unwrap();
+
+        assert_eq!("Third", list.todos[2].contents);
+        assert!(list.move_todo(&[0], &[3]).is_err());
+    }
+
+    #[test]
+    fn move_across_levels() {
+        let mut list = example_list();
+        list.add(&[1], "Second sub".to_string()).unwrap();
+
+        // First, with its sub todo, becomes the second sub todo of Second
+        list.move_todo(&[0], &[1, 1]).unwrap();
+
+        assert_eq!(1, list.todos.len());
+        assert_eq!("First", list.get(&[0, 1]).unwrap().contents);
+        assert_eq!("Sub", list.get(&[0, 1, 0]).unwrap().contents);
+
+        list.move_todo(&[0, 1, 0], &[0]).unwrap();
+
+        assert_eq!("Sub", list.todos[0].contents);
+        assert_eq!(0, list.get(&[1, 1]).unwrap().sub_todos.len());
+    }
+
+    #[test]
+    fn move_failures_change_nothing() {
+        let mut list = example_list();
+        list.add(&[1], "First".to_string()).unwrap();
+        let first = list.todos[0].clone();
+
+        assert!(list.move_todo(&[0], &[0, 0]).is_err());
+        assert!(list.move_todo(&[0], &[5, 0]).is_err());
+        assert!(matches!(
+            list.move_todo(&[1, 0], &[0]),
+            Err(WhatodoError::TodoAlreadyInList)
+        ));
+
+        assert_eq!(first, list.todos[0]);
+        assert_eq!("First", list.get(&[1, 0]).unwrap().contents);
+    }
+
+    #[test]
+    fn prioritize_todo() {
+        let mut list = example_list();
+
+        list.prioritize(&[0, 0], Some(Priority::A)).unwrap();
+
+        assert_eq!(Some(Priority::A), list.get(&[0, 0]).unwrap().priority);
+
+        list.prioritize(&[0, 0], None).unwrap();
+
+        assert_eq!(None, list.get(&[0, 0]).unwrap().priority);
+        assert!(list.prioritize(&[2], None).is_err());
+    }
+
+    #[test]
+    fn set_due_date() {
+        let mut list = example_list();
+        let due = NaiveDate::from_ymd_opt(2026, 11, 1);
+
+        list.set_due(&[1], due).unwrap();
+
+        assert_eq!(due, list.todos[1].due);
+        assert!(list.set_due(&[1, 0], due).is_err());
+    }
+
+    #[test]
+    fn ids_are_stable() {
+        let mut list = example_list();
+        let id = list.get(&[1]).unwrap().id.clone().unwrap();
+
+        assert!(id.chars().next().unwrap().is_ascii_lowercase());
+        assert_eq!(Some(vec![1]), list.find_id(&id));
+
+        list.remove(&[0]).unwrap();
+
+        assert_eq!(Some(vec![0]), list.find_id(&id));
+        assert_eq!(None, list.find_id("nope"));
+    }
+
+    #[test]
+    fn ids_assigned_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        std::fs::write(&path, "0|First|[0|Sub|]\n0|Second|id=abcde|\n").unwrap();
+
+        let first = TodoList::load(path.clone()).unwrap();
+        let second = TodoList::load(path).unwrap();
+
+        assert_eq!(first.todos, second.todos);
+        assert_eq!(Some("abcde".to_string()), first.todos[1].id);
+        // Ids only depend on the list, whichever release of Rust whatodo was built with
+        assert_eq!(Some("q2ua9".to_string()), first.todos[0].id);
+        assert!(first.get(&[0, 0]).unwrap().id.is_some());
+        assert_ne!(first.todos[0].id, first.get(&[0, 0]).unwrap().id);
+    }
+
+    #[test]
+    fn remove_done_and_todo_prune_the_tree() {
+        let mut list = example_list();
+        list.add(&[0], "Second sub".to_string()).unwrap();
+        list.add(&[1], "Sub of second".to_string()).unwrap();
+        list.add(&[1, 0], "Sub sub".to_string()).unwrap();
+
+        list.complete(&[0, 0]).unwrap();
+        list.complete(&[1]).unwrap();
+        list.complete(&[1, 0]).unwrap();
+
+        let mut done_removed = TodoList::new(PathBuf::from("todo.todos"));
+        done_removed.todos = list.todos.clone();
+        done_removed.remove_done();
+
+        // Second and its completed sub are kept for the open todo below them
+        assert_eq!(2, done_removed.todos.len());
+        assert_eq!("Second sub", done_removed.get(&[0, 0]).unwrap().contents);
+        assert_eq!("Sub sub", done_removed.get(&[1, 0, 0]).unwrap().contents);
+
+        list.remove_todo();
+
+        assert_eq!(2, list.todos.len());
+        assert_eq!(1, list.todos[0].sub_todos.len());
+        assert_eq!("Sub", list.get(&[0, 0]).unwrap().contents);
+        assert!(list.get(&[1, 0]).unwrap().sub_todos.is_empty());
+    }
+
+    #[test]
+    fn filtered_complete_and_remove() {
+        let mut list = example_list();
+        list.add(&[1], "Sub of second".to_string()).unwrap();
+
+        let filter = Filter::parse("text:sub", crate::date::today()).unwrap();
+
+        assert_eq!(vec![vec![0, 0], vec![1, 0]], list.matching(&filter));
+        assert_eq!(2, list.complete_where(&filter).unwrap());
+        assert!(list.get(&[1, 0]).unwrap().complete);
+
+        let filter = Filter::parse("status:done or text:second", crate::date::today()).unwrap();
+
+        // Both subs and then Second, left without sub todos, are removed
+        assert_eq!(3, list.remove_where(&filter));
+        assert_eq!(1, list.todos.len());
+        assert!(list.todos[0].sub_todos.is_empty());
+    }
+
+    #[test]
+    fn remove_where_keeps_unmatched_sub_todos() {
+        let mut list = example_list();
+        list.add(&[0], "Done sub".to_string()).unwrap();
+
+        list.complete(&[0]).unwrap();
+        list.complete(&[0, 1]).unwrap();
+
+        let filter = Filter::parse("status:done", crate::date::today()).unwrap();
+
+        // First is done but still holds its open sub, so only its done sub goes
+        assert_eq!(1, list.remove_where(&filter));
+        assert_eq!("First", list.todos[0].contents);
+        assert_eq!(vec!["Sub"], contents(&list.todos[0].sub_todos));
+    }
+
+    #[test]
+    fn bulk_complete_and_remove() {
+        let mut list = example_list();
+        list.add(&[], "Third".to_string()).unwrap();
+        list.add(&[0], "Second sub".to_string()).unwrap();
+
+        assert!(matches!(
+            list.complete_all(&[vec![1], vec![5]]),
+            Err(WhatodoError::IndexOutOfBounds)
+        ));
+        assert!(!list.get(&[1]).unwrap().complete);
+
+        assert_eq!(2, list.complete_all(&[vec![0, 1], vec![2]]).unwrap());
+        assert!(list.get(&[0, 1]).unwrap().complete);
+        assert!(list.get(&[2]).unwrap().complete);
+
+        // Paths refer to the list before anything is removed, in any order
+        assert_eq!(
+            3,
+            list.remove_all(&[vec![0, 0], vec![2], vec![0, 0], vec![0, 1]])
+                .unwrap()
+        );
+        assert_eq!(2, list.todos.len());
+        assert!(list.todos[0].sub_todos.is_empty());
+
+        // A todo below another removed one goes with it
+        list.add(&[1], "Sub of second".to_string()).unwrap();
+
+        assert_eq!(1, list.remove_all(&[vec![1, 0], vec![1]]).unwrap());
+        assert_eq!(
+            vec!["First"],
+            list.open().map(|t| t.contents.as_str()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn completion_is_stamped() {
+        let mut list = example_list();
+        list.policy.cascade = true;
+
+        assert!(list.get(&[0]).unwrap().created.is_some());
+
+        list.complete(&[0]).unwrap();
+
+        assert!(list.get(&[0]).unwrap().completed.is_some());
+        assert!(list.get(&[0, 0]).unwrap().completed.is_some());
+
+        list.uncomplete(&[0, 0]).unwrap();
+
+        assert_eq!(None, list.get(&[0, 0]).unwrap().completed);
+    }
+
+    #[test]
+    fn uncomplete_and_toggle() {
+        let mut list = example_list();
+
+        list.complete(&[0, 0]).unwrap();
+        list.uncomplete(&[0, 0]).unwrap();
+
+        assert!(!list.get(&[0, 0]).unwrap().complete);
+        assert!(list.toggle(&[0, 0]).unwrap());
+        assert!(!list.toggle(&[0, 0]).unwrap());
+        assert!(list.toggle(&[3]).is_err());
+    }
+
+    #[test]
+    fn complete_w_cascade() {
+        let mut list = example_list();
+        list.add(&[0, 0], "Sub sub".to_string()).unwrap();
+        list.policy.cascade = true;
+
+        list.complete(&[0]).unwrap();
+
+        assert!(list.get(&[0, 0]).unwrap().complete);
+        assert!(list.get(&[0, 0, 0]).unwrap().complete);
+
+        // Reopening only affects the todo itself
+        list.uncomplete(&[0]).unwrap();
+
+        assert!(!list.get(&[0]).unwrap().complete);
+        assert!(list.get(&[0, 0]).unwrap().complete);
+    }
+
+    #[test]
+    fn complete_w_auto_complete_parents() {
+        let mut list = example_list();
+        list.add(&[0], "Other sub".to_string()).unwrap();
+        list.add(&[0, 0], "Sub sub".to_string()).unwrap();
+        list.policy.auto_complete_parents = true;
+
+        list.complete(&[0, 0, 0]).unwrap();
+        list.complete(&[0, 1]).unwrap();
+
+        assert!(list.get(&[0, 0]).unwrap().complete);
+        assert!(list.get(&[0]).unwrap().complete);
+
+        list.uncomplete(&[0, 0, 0]).unwrap();
+
+        assert!(!list.get(&[0, 0]).unwrap().complete);
+        assert!(!list.get(&[0]).unwrap().complete);
+        assert!(list.get(&[0]).unwrap().is_partial());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        let todos = example_list().todos;
+
+        let mut list = TodoList::init(path.clone()).unwrap();
+        list.todos = todos.clone();
+        list.save().unwrap();
+
+        assert_eq!(todos, TodoList::load(path).unwrap().todos);
+    }
+
+    #[test]
+    fn archive_and_unarchive() {
+        let mut list = example_list();
+        list.add(&[0], "Second sub".to_string()).unwrap();
+        list.add(&[1], "Sub of second".to_string()).unwrap();
+
+        list.complete(&[0, 1]).unwrap();
+        list.complete(&[1]).unwrap();
+        list.complete(&[1, 0]).unwrap();
+
+        let second_sub = list.get(&[0, 1]).unwrap().clone();
+        let mut archive = TodoList::new(PathBuf::from("todo.archive"));
+
+        assert_eq!(2, list.archive_done(&mut archive));
+
+        // First stays open with its open sub, a copy of it holds the archived one
+        assert_eq!(1, list.todos.len());
+        assert_eq!(vec!["Sub"], contents(&list.todos[0].sub_todos));
+        assert_eq!(vec!["First", "Second"], contents(&archive.todos));
+        assert!(!archive.todos[0].complete);
+        assert_eq!(second_sub, archive.todos[0].sub_todos[0]);
+        assert_eq!("Sub of second", archive.get(&[1, 0]).unwrap().contents);
+
+        // Archiving First later adds it to its copy
+        list.complete(&[0, 0]).unwrap();
+        list.complete(&[0]).unwrap();
+
+        assert_eq!(1, list.archive_done(&mut archive));
+        assert!(list.todos.is_empty());
+        assert_eq!(vec!["First", "Second"], contents(&archive.todos));
+        assert!(archive.todos[0].complete);
+        assert_eq!(
+            vec!["Second sub", "Sub"],
+            contents(&archive.todos[0].sub_todos)
+        );
+
+        let id = second_sub.id.clone().unwrap();
+
+        list.unarchive(&mut archive, &id).unwrap();
+        assert_eq!("First", list.todos[0].contents);
+        assert_eq!(second_sub, list.todos[0].sub_todos[0]);
+        assert_eq!(vec!["Sub"], contents(&archive.todos[0].sub_todos));
+
+        // Unarchiving First brings back its other sub under the one already restored
+        let id = archive.todos[0].id.clone().unwrap();
+
+        list.unarchive(&mut archive, &id).unwrap();
+        assert_eq!(1, list.todos.len());
+        assert_eq!(
+            vec!["Second sub", "Sub"],
+            contents(&list.todos[0].sub_todos)
+        );
+        assert_eq!(vec!["Second"], contents(&archive.todos));
+
+        assert!(matches!(
+            list.unarchive(&mut archive, &id),
+            Err(WhatodoError::NoSuchId(_))
+        ));
+    }
+
+    #[test]
+    fn unarchive_drops_empty_copies() {
+        let mut list = example_list();
+        list.complete(&[0, 0]).unwrap();
+
+        let mut archive = TodoList::new(PathBuf::from("todo.archive"));
+        list.archive_done(&mut archive);
+
+        let id = archive.todos[0].sub_todos[0].id.clone().unwrap();
+
+        list.unarchive(&mut archive, &id).unwrap();
+        assert!(archive.todos.is_empty());
+        assert_eq!(vec!["Sub"], contents(&list.todos[0].sub_todos));
+    }
+
+    #[test]
+    fn unarchive_under_moved_parent() {
+        let mut list = example_list();
+        list.complete(&[0, 0]).unwrap();
+
+        let mut archive = TodoList::new(PathBuf::from("todo.archive"));
+        list.archive_done(&mut archive);
+
+        // First is moved under Second after Sub was archived from under it
+        list.move_todo(&[0], &[1, 0]).unwrap();
+
+        let id = archive.todos[0].sub_todos[0].id.clone().unwrap();
+
+        list.unarchive(&mut archive, &id).unwrap();
+        assert_eq!(vec!["Second"], contents(&list.todos));
+        assert_eq!(vec!["Sub"], contents(&list.get(&[0, 0]).unwrap().sub_todos));
+        assert!(archive.todos.is_empty());
+    }
+
+    #[test]
+    fn unarchive_drops_completed_copies() {
+        let mut list = example_list();
+        list.add(&[0], "Open sub".to_string()).unwrap();
+        list.complete(&[0, 0]).unwrap();
+        list.get_mut(&[0]).unwrap().complete = true;
+
+        // First is completed but still holds an open sub, so only a copy of it is archived
+        let mut archive = TodoList::new(PathBuf::from("todo.archive"));
+        assert_eq!(1, list.archive_done(&mut archive));
+        assert!(archive.todos[0].complete);
+
+        let id = archive.todos[0].sub_todos[0].id.clone().unwrap();
+
+        list.unarchive(&mut archive, &id).unwrap();
+        assert!(archive.todos.is_empty());
+        assert_eq!(vec!["Open sub", "Sub"], contents(&list.todos[0].sub_todos));
+    }
+
+    #[test]
+    fn unarchive_rejects_duplicates() {
+        let mut list = example_list();
+        list.complete(&[1]).unwrap();
+
+        let mut archive = TodoList::new(PathBuf::from("todo.archive"));
+        list.archive_done(&mut archive);
+        list.add(&[], "Second".to_string()).unwrap();
+
+        let id = archive.todos[0].id.clone().unwrap();
+
+        assert!(matches!(
+            list.unarchive(&mut archive, &id),
+            Err(WhatodoError::TodoAlreadyInList)
+        ));
+        assert_eq!(vec!["Second"], contents(&archive.todos));
+        assert_eq!(vec!["First", "Second"], contents(&list.todos));
+    }
+
+    #[test]
+    fn undo_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        let mut list = TodoList::init(path.clone()).unwrap();
+        list.todos = example_list().todos;
+        list.complete(&[1]).unwrap();
+        list.save().unwrap();
+
+        let mut archive = list.load_archive().unwrap();
+        list.action = Some("archive".to_string());
+        list.archive_done(&mut archive);
+        archive.save().unwrap();
+        list.save().unwrap();
+
+        assert_eq!(
+            vec!["Second"],
+            contents(&list.load_archive().unwrap().todos)
+        );
+
+        list.undo().unwrap();
+
+        assert_eq!(vec!["First", "Second"], contents(&list.todos));
+        assert!(list.load_archive().unwrap().todos.is_empty());
+
+        list.redo().unwrap();
+
+        assert_eq!(vec!["First"], contents(&list.todos));
+        assert_eq!(
+            vec!["Second"],
+            contents(&list.load_archive().unwrap().todos)
+        );
+    }
+
+    #[test]
+    fn failed_save_is_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        let mut list = TodoList::init(path.clone()).unwrap();
+        list.action = Some("add First".to_string());
+        list.add(&[], "First".to_string()).unwrap();
+
+        // A directory in the way of the list makes writing it fail
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+
+        assert!(matches!(list.save(), Err(WhatodoError::CannotSaveTodos(_))));
+        assert!(list.history().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        let mut list = TodoList::init(path.clone()).unwrap();
+        list.action = Some("add First".to_string());
+        list.add(&[], "First".to_string()).unwrap();
+        list.save().unwrap();
+
+        // Saves without a change, or without an action, are not recorded
+        list.save().unwrap();
+        list.action = None;
+        list.complete(&[0]).unwrap();
+        list.save().unwrap();
+
+        let mut list = TodoList::load(path.clone()).unwrap();
+        list.action = Some("remove all".to_string());
+        list.clear();
+        list.save().unwrap();
+
+        assert_eq!(2, list.history().unwrap().entries().count());
+
+        let mut list = TodoList::load(path.clone()).unwrap();
+
+        assert_eq!("remove all", list.undo().unwrap().action);
+        assert!(list.todos[0].complete);
+        assert_eq!(list.todos, TodoList::load(path.clone()).unwrap().todos);
+
+        assert_eq!("add First", list.undo().unwrap().action);
+        assert!(list.todos.is_empty());
+        assert!(matches!(list.undo(), Err(WhatodoError::NothingToUndo)));
+
+        assert_eq!("add First", list.redo().unwrap().action);
+        // The list comes back as it was when the change was undone
+        assert_eq!("First", list.todos[0].contents);
+        assert!(list.todos[0].complete);
+        assert_eq!(list.todos, TodoList::load(path.clone()).unwrap().todos);
+
+        // Changing the list forgets what was undone
+        list.action = Some("add Second".to_string());
+        list.add(&[], "Second".to_string()).unwrap();
+        list.save().unwrap();
+
+        assert!(matches!(list.redo(), Err(WhatodoError::NothingToRedo)));
+    }
+}
@@ -1,259 +1,1112 @@
 // All indices used below will be 1 indexed, as not all userers are to be assumed to know of 0 indexing
+// Todos are given by their path, indices separated by dots (2.1 is the second todo's first subtodo)
+// Negative indices count from the end, -1 is the last todo and 2.-1 the second todo's last subtodo
 // whatodo add "Make that one function"    | Add a top level todo with contents indicated in quotations
 // whatodo add 1 "A sub todo"              | Add a subtodo to the first top level todo with contents indicated in quotations
-// whatodo add 1 1 "A sub todo's sub todo" | Add a subtodo to the first top level todo's first subtodo with contents indicated in quotations
-// whatodo checkout all                    | Prints all todos
-// whatodo checkout done                   | Prints all todos marked done
-// whatodo checkout todo                   | Prints all todos not marked done
+// whatodo add 1.1 "A sub todo's sub todo" | Add a subtodo to the first top level todo's first subtodo with contents indicated in quotations
+// whatodo add -p A "Urgent thing"         | Add a top level todo with priority A (A, B or C)
+// whatodo prioritize 1.1 B                | Sets the priority of the first todo's first subtodo, none removes it
+// whatodo add --due fri "Send report"     | Add a top level todo due next friday
+// whatodo due 1 2026-11-01                | Sets the due date of the first todo, none removes it
+// whatodo add "Fix login +api @office"    | Add a top level todo tagged api in the office context
+// whatodo checkout all                    | Prints all todos, each with its full path such as 3.2, in every view
+// whatodo checkout all --tag backend      | Prints todos tagged backend, along with their parents
+// whatodo checkout todo --context office  | Prints todos not marked done in the office context, along with their parents
+// whatodo checkout overdue                | Prints todos not marked done which are past their due date
+// whatodo checkout today                  | Prints todos not marked done which are due today
+// whatodo checkout upcoming               | Prints todos not marked done which are due after today, soonest first
+// whatodo checkout all --sort priority    | Prints all todos, most important first at every level
+// whatodo checkout todo --priority A      | Prints todos not marked done with priority A
+// whatodo checkout --where 'status:open and (tag:api or priority:A) and due<7d'
+//                                         | Prints todos matching the filter, along with their parents
+// whatodo complete --where 'tag:release'  | Marks every todo matching the filter as complete
+// whatodo remove --where 'status:done and done<-30d'
+//                                         | Deletes every todo matching the filter, unless it holds todos which don't
+// whatodo checkout done                   | Prints all todos marked done at any depth, along with their parents
+// whatodo checkout todo                   | Prints all todos not marked done at any depth, along with their parents
 // whatodo complete 1                      | Marks first todo as complete
-// whatodo complete 1 1                    | Marks first todo's first subtodo as complete
+// whatodo complete 1.1                    | Marks first todo's first subtodo as complete
+// whatodo uncomplete 1                    | Marks first todo as not complete
+// whatodo toggle 1.1                      | Flips whether first todo's first subtodo is complete
+// whatodo complete 1 --cascade            | Marks first todo and every todo below it as complete
+// whatodo complete 1.1 --auto-parents     | Also marks first todo complete once all its subtodos are
 // whatodo remove 1                        | Deletes first todo, will use 1 indexed list
-// whatodo remove done                     | Deletes all todos that are marked as completed
-// whatodo remove todos                    | Deletes all todos that are not completed
+// whatodo remove -1                       | Deletes the last todo
+// whatodo complete 1 3 5                  | Marks first, third and fifth todos as complete
+// whatodo remove 2-6                      | Deletes second to sixth todos, every path refers to the list before removing
+// whatodo complete 2.*                    | Marks every subtodo of the second todo as complete
+// whatodo remove done                     | Deletes todos at any depth marked as completed, unless they hold open subtodos
+// whatodo remove todo                     | Deletes todos at any depth not completed, unless they hold completed subtodos
 // whatodo remove all                      | Deletes all todos from the current list
-// whatodo remove 1 1                      | Deletes the first subtodo of the first todo
-// whatodo init                            | Creates new list in current directory
+// whatodo remove 1.1                      | Deletes the first subtodo of the first todo
+// whatodo edit 1.1 "New text"             | Changes the text of the first todo's first subtodo, adding any +tags and @contexts
+// whatodo edit 1                          | Changes the text of the first todo in $EDITOR
+// whatodo note 1.2                        | Writes notes for the first todo's second subtodo in $EDITOR
+// whatodo note 1.2 -                      | Same, reading the notes from stdin instead
+// whatodo show 1.2                        | Prints everything about the first todo's second subtodo, notes included
+// whatodo move 3 to 1.2                   | Moves third todo, with its subtodos, to be first todo's second subtodo
+// whatodo up 2.1                          | Moves second todo's first subtodo one place up, same for down
+// whatodo top 2.1                         | Moves second todo's first subtodo to the top, same for bottom
+// whatodo complete k3x9a                  | Marks the todo with id k3x9a as complete, ids work wherever paths do
+// whatodo checkout all --ids              | Prints all todos along with their ids
+// whatodo checkout all --ages             | Prints all todos along with how long ago they were added or completed
+// whatodo checkout done --since mon       | Prints todos completed since monday, --until limits the other end
+// whatodo search login                    | Prints todos containing login in their text or notes, with their paths
+// whatodo search -i login                 | Same, ignoring case
+// whatodo search --regex '^fix (a|b)'     | Prints todos whose text or notes match the regular expression
+// whatodo search -- --draft               | Searches for text starting with -, everything after -- is the query
+// whatodo archive                         | Moves todos remove done would delete, with their subtodos, to todo.archive
+// whatodo checkout archive                | Prints all archived todos along with their ids
+// whatodo search --archive login          | Searches the archived todos instead, showing their ids
+// whatodo unarchive k3x9a                 | Moves the archived todo with id k3x9a, with its subtodos, back to the list
+// whatodo undo                            | Reverts the latest change to the list, undo 3 reverts the latest three
+// whatodo redo                            | Makes the latest undone change again
+// whatodo history                         | Prints the changes which can be undone or redone, newest first
+// whatodo init                            | Creates new list in current directory, unless there already is one
+// whatodo --file path/to/list.todos ...   | Uses the given list instead of searching for todo.todos
+// whatodo --global ...                    | Uses the user level list instead of searching for todo.todos
+// whatodo checkout all --with-global      | Prints all todos of this directory's list and of the global list
 
 use std::{
-    env,
-    fs::File,
-    io::{Read, Write},
+    env, fs,
+    io::{self, Read},
+    iter::Peekable,
+    path::PathBuf,
 };
 
+use chrono::NaiveDate;
+
 use whatodo::{
+    date, editor,
     error::WhatodoError,
-    todo::{from_todo_string, Todo},
-    utils,
+    filter::Filter,
+    history,
+    list::TodoList,
+    path::TodoPath,
+    search::{self, Matcher},
+    storage,
+    todo::{DisplayOptions, Priority, Todo},
+    view::{self, ViewNode},
 };
 
 type Result<T> = std::result::Result<T, WhatodoError>;
 
-fn load_todos() -> Result<Vec<Todo>> {
-    let mut f = match File::open("todo.todos") {
-        Ok(file) => file,
-        Err(e) => {
-            return Err(WhatodoError::CannotLoadTodos(e));
+// Options given before the command, they apply to every command
+#[derive(Default)]
+struct Options {
+    file: Option<PathBuf>,
+    global: bool,
+}
+
+fn parse_options<I: Iterator<Item = String>>(args: &mut Peekable<I>) -> Result<Options> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next_if(|a| a.starts_with("--")) {
+        match arg.split_once('=') {
+            None if arg == "--global" => options.global = true,
+            Some(("--file", path)) => options.file = Some(PathBuf::from(path)),
+            None if arg == "--file" => match args.next() {
+                Some(path) => options.file = Some(PathBuf::from(path)),
+                None => return Err(WhatodoError::NotEnoughArguments),
+            },
+            _ => return Err(WhatodoError::InvalidCommand),
+        }
+    }
+
+    if options.file.is_none() {
+        options.file = env::var_os("WHATODO_FILE").map(PathBuf::from);
+    }
+
+    Ok(options)
+}
+
+fn global_path() -> Result<PathBuf> {
+    storage::global_todos_path().ok_or(WhatodoError::NoGlobalList)
+}
+
+fn load_todos(options: &Options) -> Result<TodoList> {
+    let path = match &options.file {
+        _ if options.global => global_path()?,
+        Some(path) => path.clone(),
+        None => {
+            let cwd = env::current_dir().map_err(WhatodoError::CannotLoadTodos)?;
+
+            storage::find_todos_file(&cwd).ok_or(WhatodoError::NoListFound)?
         }
     };
 
-    let mut todo_string = String::new();
+    let mut todos_list = TodoList::load(path)?;
 
-    match f.read_to_string(&mut todo_string) {
-        // Get all of the todos from the file
-        Ok(_) => (),
-        Err(e) => {
-            return Err(WhatodoError::CannotLoadTodos(e));
+    // Keep todo.todos.bak around only when asked for, to avoid cluttering every project
+    todos_list.backup = env::var_os("WHATODO_BACKUP").is_some();
+    todos_list.policy.cascade = env::var_os("WHATODO_CASCADE").is_some();
+    todos_list.policy.auto_complete_parents =
+        env::var_os("WHATODO_AUTO_COMPLETE_PARENTS").is_some();
+
+    Ok(todos_list)
+}
+
+fn init_new_list(options: &Options) -> Result<()> {
+    let path = match &options.file {
+        _ if options.global => global_path()?,
+        Some(path) => path.clone(),
+        None => PathBuf::from(storage::TODOS_FILE_NAME),
+    };
+
+    let todos_list = TodoList::init(path)?;
+
+    println!(
+        "Created todo list at {}",
+        fs::canonicalize(todos_list.path())
+            .unwrap_or_else(|_| todos_list.path().to_path_buf())
+            .display()
+    );
+
+    Ok(())
+}
+
+fn take_flag(args: &mut Vec<String>, names: &[&str]) -> Result<Option<String>> {
+    //! Removes a flag which takes a value, given as --flag value or --flag=value, from args and
+    //! returns its value.
+    for (ind, arg) in args.iter().enumerate() {
+        if names.contains(&arg.as_str()) {
+            if ind + 1 == args.len() {
+                return Err(WhatodoError::NotEnoughArguments);
+            }
+
+            let value = args.remove(ind + 1);
+            args.remove(ind);
+
+            return Ok(Some(value));
+        }
+
+        if let Some((name, value)) = arg.split_once('=') {
+            if names.contains(&name) {
+                let value = value.to_string();
+                args.remove(ind);
+
+                return Ok(Some(value));
+            }
         }
     }
 
-    let mut todos: Vec<Todo> = Vec::new();
+    Ok(None)
+}
 
-    // Loads todos read in from file
-    for str in todo_string.lines().into_iter().filter(|s| !s.is_empty()) {
-        todos.push(from_todo_string(str.to_string()));
+fn parse_priority(value: &str) -> Result<Option<Priority>> {
+    match value {
+        "none" => Ok(None),
+        _ => match value.parse() {
+            Ok(priority) => Ok(Some(priority)),
+            Err(_) => Err(WhatodoError::InvalidPriority(value.to_string())),
+        },
     }
+}
 
-    Ok(todos)
+fn parse_due(value: &str) -> Result<Option<NaiveDate>> {
+    match value {
+        "none" => Ok(None),
+        _ => match date::parse_date(value, date::today()) {
+            Some(due) => Ok(Some(due)),
+            None => Err(WhatodoError::InvalidDate(value.to_string())),
+        },
+    }
 }
 
-fn init_new_list() -> Result<()> {
-    match File::create("todo.todos") {
-        Ok(_) => Ok(()),
-        Err(e) => Err(WhatodoError::CannotInitTodos(e)),
+fn take_filter(args: &mut Vec<String>) -> Result<Option<Filter>> {
+    //! Removes --where and its filter expression from args, returning the parsed filter.
+    match take_flag(args, &["--where", "-w"])? {
+        Some(value) => Filter::parse(&value, date::today())
+            .map(Some)
+            .map_err(WhatodoError::InvalidFilter),
+        None => Ok(None),
     }
 }
 
-// There can exist multiple sub todos that are the same, but no base level todos may be the same
-fn add_to_list(mut todos_list: Vec<Todo>, args: Vec<String>) -> Result<()> {
-    let depth_list = utils::depth_iterator_from_args_to_item(args.iter().peekable());
+fn resolve_item(todos_list: &TodoList, arg: &str) -> Result<Vec<usize>> {
+    //! The 0 indexed path of the todo given by arg, either a path such as 2.1.3 or -1, or an id.
+    if arg.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        return todos_list.resolve(&arg.parse::<TodoPath>()?);
+    }
 
-    match args.last() {
-        Some(value) => {
-            let new_todo = Todo::new(None, value.clone());
+    let id = arg.strip_prefix('#').unwrap_or(arg);
 
-            let mut curr_root = &mut todos_list;
+    todos_list
+        .find_id(id)
+        .ok_or_else(|| WhatodoError::NoSuchId(id.to_string()))
+}
 
-            for ind in depth_list {
-                curr_root = match curr_root.get_mut(ind) {
-                    Some(node) => &mut node.sub_todos,
-                    None => {
-                        return Err(WhatodoError::IndexOutOfBounds);
-                    }
-                }
-            }
+fn selected_items(todos_list: &TodoList, args: &[String]) -> Result<Vec<Vec<usize>>> {
+    //! The paths of every todo given by args. Each is a path, a range of sibling todos such as
+    //! 2-6 or 1.2-4, all sub todos of a todo such as 2.*, or an id.
+    if args.is_empty() {
+        return Err(WhatodoError::NotEnoughArguments);
+    }
 
-            if !utils::search_all_todos_content(&curr_root, &value) {
-                curr_root.push(new_todo);
-            } else {
-                return Err(WhatodoError::TodoAlreadyInList);
-            }
+    let mut items = Vec::new();
 
-            save_todos(todos_list)
+    for arg in args {
+        match arg.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '*') {
+            true => items.extend(todos_list.select(&arg.parse()?)?),
+            false => items.push(resolve_item(todos_list, arg)?),
         }
-        None => Err(WhatodoError::NotEnoughArguments),
     }
+
+    Ok(items)
 }
 
-fn checkout_list(todos_list: Vec<Todo>, option: String) -> Result<()> {
-    if todos_list.len() == 0 {
-        println!("There are no todos!");
-    } else {
-        match option.as_str() {
-            "all" => {
-                for (ind, todo) in todos_list.iter().enumerate() {
-                    println!("{}. {}", ind + 1, todo.to_enumerated_string(None));
-                }
-            }
-            "done" => {
-                for todo in todos_list.iter().filter(|e| e.complete) {
-                    println!("{}", todo.to_string());
-                }
-            }
-            "todo" => {
-                for (ind, todo) in todos_list.iter().filter(|e| !e.complete).enumerate() {
-                    println!("{}. {}", ind + 1, todo.to_enumerated_string(None));
-                }
-            }
-            _ => {
-                return Err(WhatodoError::InvalidCommand);
-            }
+fn parse_since(value: &str) -> Result<NaiveDate> {
+    date::parse_past_date(value, date::today())
+        .ok_or_else(|| WhatodoError::InvalidDate(value.to_string()))
+}
+
+fn add_to_list(mut todos_list: TodoList, mut args: Vec<String>) -> Result<()> {
+    let priority = match take_flag(&mut args, &["--priority", "-p"])? {
+        Some(value) => parse_priority(&value)?,
+        None => None,
+    };
+
+    let due = match take_flag(&mut args, &["--due", "-d"])? {
+        Some(value) => parse_due(&value)?,
+        None => None,
+    };
+
+    let mut tags = Vec::new();
+    while let Some(tag) = take_flag(&mut args, &["--tag", "-t"])? {
+        tags.push(tag);
+    }
+
+    let mut contexts = Vec::new();
+    while let Some(context) = take_flag(&mut args, &["--context", "-c"])? {
+        contexts.push(context);
+    }
+
+    // The text is always last, so text which looks like a path is never taken as one
+    let (parent, value) = match args.as_slice() {
+        [value] => (Vec::new(), value),
+        [parent, value] => (resolve_item(&todos_list, parent)?, value),
+        [] => return Err(WhatodoError::NotEnoughArguments),
+        _ => return Err(WhatodoError::InvalidCommand),
+    };
+
+    let mut new_todo = Todo::from_text(None, value);
+    new_todo.priority = priority;
+    new_todo.due = due;
+    new_todo.tags.extend(tags);
+    new_todo.contexts.extend(contexts);
+
+    if new_todo.contents.is_empty() {
+        return Err(WhatodoError::EmptyTodo);
+    }
+
+    todos_list.add_todo(&parent, new_todo)?;
+
+    todos_list.save()
+}
+
+fn prioritize_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    match args.as_slice() {
+        [item, value] => {
+            let item = resolve_item(&todos_list, item)?;
+
+            todos_list.prioritize(&item, parse_priority(value)?)?;
+
+            todos_list.save()
+        }
+        [] | [_] => Err(WhatodoError::NotEnoughArguments),
+        _ => Err(WhatodoError::InvalidCommand),
+    }
+}
+
+fn edit_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let item = match args.first() {
+        Some(item) => resolve_item(&todos_list, item)?,
+        None => return Err(WhatodoError::NotEnoughArguments),
+    };
+
+    // Anything after the path is the new text, otherwise it is written in the editor
+    let contents = match &args[1..] {
+        [contents] => contents.clone(),
+        [] => {
+            let current = match todos_list.get(&item) {
+                Some(todo) => todo.contents.clone(),
+                None => return Err(WhatodoError::IndexOutOfBounds),
+            };
+
+            editor::edit_text(&current)?.trim_end().to_string()
+        }
+        _ => return Err(WhatodoError::InvalidCommand),
+    };
+
+    todos_list.edit(&item, contents)?;
+
+    todos_list.save()
+}
+
+fn single_item(todos_list: &TodoList, args: &[String]) -> Result<Vec<usize>> {
+    //! The path of the todo given as the only argument
+    match args {
+        [item] => resolve_item(todos_list, item),
+        [] => Err(WhatodoError::NotEnoughArguments),
+        _ => Err(WhatodoError::InvalidCommand),
+    }
+}
+
+fn write_notes(mut todos_list: TodoList, mut args: Vec<String>) -> Result<()> {
+    let before = args.len();
+    args.retain(|a| a != "-" && a != "--stdin");
+    let from_stdin = args.len() < before;
+
+    let item = single_item(&todos_list, &args)?;
+
+    let current = match todos_list.get(&item) {
+        Some(todo) => todo.notes.clone().unwrap_or_default(),
+        None => return Err(WhatodoError::IndexOutOfBounds),
+    };
+
+    // Notes read from stdin replace the current ones, otherwise they are written in the editor
+    let notes = match from_stdin {
+        false => editor::edit_text(&current)?,
+        true => {
+            let mut notes = String::new();
+
+            io::stdin()
+                .read_to_string(&mut notes)
+                .map_err(WhatodoError::CannotOpenEditor)?;
+
+            notes
+        }
+    };
+
+    // Leaving the notes empty removes them
+    let notes = Some(notes.trim_end().to_string()).filter(|n| !n.is_empty());
+
+    todos_list.set_notes(&item, notes)?;
+
+    todos_list.save()
+}
+
+fn show_todo(todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let item = single_item(&todos_list, &args)?;
+
+    match todos_list.get(&item) {
+        Some(todo) => {
+            println!("{}", view::render_details(&item, todo));
+
+            Ok(())
+        }
+        None => Err(WhatodoError::IndexOutOfBounds),
+    }
+}
+
+fn step_history(mut todos_list: TodoList, args: Vec<String>, redo: bool) -> Result<()> {
+    let count = match args.as_slice() {
+        [] => 1,
+        [count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => return Err(WhatodoError::InvalidCommand),
+        },
+        _ => return Err(WhatodoError::InvalidCommand),
+    };
+
+    for step in 0..count {
+        let result = match redo {
+            true => todos_list.redo(),
+            false => todos_list.undo(),
+        };
+
+        let entry = match result {
+            Ok(entry) => entry,
+            // Stepping back further than the history goes stops at its end
+            Err(WhatodoError::NothingToUndo | WhatodoError::NothingToRedo) if step > 0 => break,
+            Err(e) => return Err(e),
+        };
+
+        match redo {
+            true => println!("Redid: {}", entry.action),
+            false => println!("Undid: {}", entry.action),
         }
     }
 
     Ok(())
 }
 
-fn complete_todo(mut todos_list: Vec<Todo>, args: Vec<String>) -> Result<()> {
-    let item_to_complete = utils::get_mut_from_num_depth(
-        &mut todos_list,
-        &utils::depth_iterator_from_args_to_item(args.iter().peekable()),
-    );
+fn print_history(todos_list: TodoList) -> Result<()> {
+    let history = todos_list.history()?;
 
-    match item_to_complete {
-        Some(todo) => todo.complete = true,
-        None => {
-            return Err(WhatodoError::IndexOutOfBounds);
+    match history.is_empty() {
+        true => println!("No changes recorded"),
+        false => println!("{}", history::render(&history)),
+    }
+
+    Ok(())
+}
+
+fn move_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    // The to between the paths is optional
+    let (from, to) = match args.as_slice() {
+        [from, to] => (from, to),
+        [from, word, to] if word == "to" => (from, to),
+        [] | [_] => return Err(WhatodoError::NotEnoughArguments),
+        _ => return Err(WhatodoError::InvalidCommand),
+    };
+
+    let from = resolve_item(&todos_list, from)?;
+    let to = resolve_item(&todos_list, to)?;
+
+    todos_list.move_todo(&from, &to)?;
+
+    todos_list.save()
+}
+
+fn reorder_todo(mut todos_list: TodoList, direction: &str, args: Vec<String>) -> Result<()> {
+    //! Moves an item within the list it resides in, direction is one of up, down, top or bottom.
+    let from = single_item(&todos_list, &args)?;
+
+    let (index, parent) = match from.split_last() {
+        Some((index, parent)) => (*index, parent),
+        None => return Err(WhatodoError::IndexOutOfBounds),
+    };
+
+    let last = match todos_list.sibling_count(&from) {
+        Some(count) if index < count => count - 1,
+        _ => return Err(WhatodoError::IndexOutOfBounds),
+    };
+
+    let new_index = match direction {
+        "up" => index.checked_sub(1).ok_or(WhatodoError::IndexOutOfBounds)?,
+        "down" if index < last => index + 1,
+        "down" => return Err(WhatodoError::IndexOutOfBounds),
+        "top" => 0,
+        _ => last,
+    };
+
+    let mut to = parent.to_vec();
+    to.push(new_index);
+
+    todos_list.move_todo(&from, &to)?;
+
+    todos_list.save()
+}
+
+fn set_due_date(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    match args.as_slice() {
+        [item, value] => {
+            let item = resolve_item(&todos_list, item)?;
+
+            todos_list.set_due(&item, parse_due(value)?)?;
+
+            todos_list.save()
         }
+        [] | [_] => Err(WhatodoError::NotEnoughArguments),
+        _ => Err(WhatodoError::InvalidCommand),
     }
+}
 
-    save_todos(todos_list)
+struct CheckoutOptions {
+    option: String,
+    with_global: bool,
+    sort_by_priority: bool,
+    priority: Option<Priority>,
+    tag: Option<String>,
+    context: Option<String>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    filter: Option<Filter>,
+    display: DisplayOptions,
 }
 
-fn remove_from_list(mut todos_list: Vec<Todo>, args: Vec<String>) -> Result<()> {
-    match args.first() {
-        Some(first) => {
-            match first.as_str() {
-                "all" => save_todos(Vec::new()),
-                "done" => save_todos(todos_list.into_iter().filter(|t| !t.complete).collect()),
-                "todo" => save_todos(todos_list.into_iter().filter(|t| t.complete).collect()),
-                // Check to see if it's a depth thing
-                _ => {
-                    let mut depth_list =
-                        utils::depth_iterator_from_args_to_parent(args.iter().peekable())
-                            .into_iter();
+fn parse_checkout_options(mut args: Vec<String>) -> Result<CheckoutOptions> {
+    let sort_by_priority = match take_flag(&mut args, &["--sort"])?.as_deref() {
+        Some("priority") => true,
+        Some(_) => return Err(WhatodoError::InvalidCommand),
+        None => false,
+    };
 
-                    let mut curr_root = &mut todos_list;
+    let priority = match take_flag(&mut args, &["--priority", "-p"])? {
+        Some(value) => parse_priority(&value)?,
+        None => None,
+    };
 
-                    while let Some(ind) = depth_list.next() {
-                        match curr_root.get_mut(ind) {
-                            Some(node) => curr_root = &mut node.sub_todos,
-                            None => {
-                                return Err(WhatodoError::IndexOutOfBounds);
-                            }
-                        }
-                    }
+    let tag = take_flag(&mut args, &["--tag", "-t"])?;
+    let context = take_flag(&mut args, &["--context", "-c"])?;
+    let filter = take_filter(&mut args)?;
 
-                    let index_to_remove = args.last().unwrap().parse::<usize>().unwrap() - 1;
+    let since = take_flag(&mut args, &["--since"])?
+        .map(|value| parse_since(&value))
+        .transpose()?;
 
-                    match curr_root.get(index_to_remove) {
-                        Some(_) => curr_root.remove(index_to_remove),
-                        None => {
-                            return Err(WhatodoError::IndexOutOfBounds);
-                        }
-                    };
+    let until = take_flag(&mut args, &["--until"])?
+        .map(|value| parse_since(&value))
+        .transpose()?;
 
-                    save_todos(todos_list)
-                }
-            }
+    let with_global = args.iter().any(|a| a == "--with-global");
+    let display = DisplayOptions {
+        ids: args.iter().any(|a| a == "--ids"),
+        ages: match args.iter().any(|a| a == "--ages") {
+            true => Some(date::now()),
+            false => None,
+        },
+    };
+
+    let option = match args.iter().find(|a| !a.starts_with("--")) {
+        Some(arg) => arg.clone(),
+        None => "all".to_string(),
+    };
+
+    Ok(CheckoutOptions {
+        option,
+        with_global,
+        sort_by_priority,
+        priority,
+        tag,
+        context,
+        since,
+        until,
+        filter,
+        display,
+    })
+}
+
+fn select_nodes<'a>(
+    todos_list: &'a TodoList,
+    checkout: &CheckoutOptions,
+) -> Result<Vec<ViewNode<'a>>> {
+    let mut nodes = view::tree(&todos_list.todos);
+    let today = date::today();
+
+    match checkout.option.as_str() {
+        "all" => (),
+        "done" => nodes = view::prune(nodes, &|todo| todo.complete),
+        "todo" => nodes = view::prune(nodes, &|todo| !todo.complete),
+        "overdue" => nodes = view::prune(nodes, &|todo| todo.is_overdue(today)),
+        "today" => nodes = view::prune(nodes, &|todo| !todo.complete && todo.due == Some(today)),
+        "upcoming" => {
+            nodes = view::prune(nodes, &|todo| {
+                !todo.complete && todo.due.is_some_and(|due| due > today)
+            });
+
+            view::sort_by_key(&mut nodes, &|todo| (todo.due.is_none(), todo.due));
+        }
+        _ => {
+            return Err(WhatodoError::InvalidCommand);
         }
-        None => Err(WhatodoError::NotEnoughArguments),
     }
+
+    if let Some(priority) = checkout.priority {
+        nodes = view::prune(nodes, &|todo| todo.priority == Some(priority));
+    }
+
+    if let Some(tag) = &checkout.tag {
+        nodes = view::prune(nodes, &|todo| todo.tags.contains(tag));
+    }
+
+    if let Some(context) = &checkout.context {
+        nodes = view::prune(nodes, &|todo| todo.contexts.contains(context));
+    }
+
+    if checkout.since.is_some() || checkout.until.is_some() {
+        nodes = view::prune(nodes, &|todo| {
+            todo.completed_between(checkout.since, checkout.until)
+        });
+    }
+
+    if let Some(filter) = &checkout.filter {
+        nodes = view::prune(nodes, &|todo| filter.matches(todo));
+    }
+
+    if checkout.sort_by_priority {
+        view::sort_by_priority(&mut nodes);
+    }
+
+    Ok(nodes)
 }
 
-fn save_todos(todos_list: Vec<Todo>) -> Result<()> {
-    let mut otf = match File::create("todo.todos") {
-        Ok(file) => file,
-        Err(e) => {
-            return Err(WhatodoError::CannotSaveTodos(e));
+fn print_list(todos_list: &TodoList, checkout: &CheckoutOptions) -> Result<()> {
+    // Every listing, filtered and sorted or not, shows the path each todo is stored at
+    let nodes = select_nodes(todos_list, checkout)?;
+
+    if todos_list.is_empty() {
+        println!("There are no todos!");
+    } else if nodes.is_empty() {
+        println!("There are no matching todos!");
+    } else {
+        println!("{}", view::render_with(&nodes, &checkout.display));
+    }
+
+    Ok(())
+}
+
+fn archive_display(display: DisplayOptions, archive: bool) -> DisplayOptions {
+    //! Archived todos are brought back by id, so their ids are always shown when listing them
+    DisplayOptions {
+        ids: display.ids || archive,
+        ..display
+    }
+}
+
+fn checkout_list(todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let checkout = parse_checkout_options(args)?;
+
+    if checkout.option == "archive" {
+        let archive = todos_list.load_archive()?;
+        let checkout = CheckoutOptions {
+            option: "all".to_string(),
+            display: archive_display(checkout.display, true),
+            ..checkout
+        };
+
+        return print_list(&archive, &checkout);
+    }
+
+    if !checkout.with_global {
+        return print_list(&todos_list, &checkout);
+    }
+
+    // Loaded before printing anything, so a missing global list doesn't follow half the output
+    let global_path = global_path()?;
+
+    if !global_path.exists() {
+        return Err(WhatodoError::NoGlobalListFound);
+    }
+
+    // With --global, or inside the directory the global list is kept in, both are the same list
+    if fs::canonicalize(&global_path).ok() == fs::canonicalize(todos_list.path()).ok() {
+        return print_list(&todos_list, &checkout);
+    }
+
+    let global_list = TodoList::load(global_path)?;
+
+    // Both lists are labelled, indices shown under the global heading refer to the --global list
+    println!("Project list ({}):", todos_list.path().display());
+    print_list(&todos_list, &checkout)?;
+
+    println!();
+    println!("Global list ({}):", global_list.path().display());
+    print_list(&global_list, &checkout)
+}
+
+fn search_list(todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let (mut regex, mut ignore_case, mut archive) = (false, false, false);
+    let mut queries = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--regex" | "-r" => regex = true,
+            "--ignore-case" | "-i" => ignore_case = true,
+            "--archive" => archive = true,
+            // Everything after -- is taken as is, so queries starting with - can be searched
+            "--" => queries.extend(args.by_ref()),
+            flag if flag.starts_with('-') => return Err(WhatodoError::InvalidCommand),
+            _ => queries.push(arg),
         }
+    }
+
+    let query = match queries.as_slice() {
+        [query] => query,
+        [] => return Err(WhatodoError::NotEnoughArguments),
+        _ => return Err(WhatodoError::InvalidCommand),
     };
 
-    for todo in todos_list {
-        match otf.write(format!("{}\n", todo.to_todos()).as_bytes()) {
-            Ok(_) => (),
-            Err(e) => {
-                return Err(WhatodoError::CannotSaveTodos(e));
+    let todos_list = match archive {
+        true => todos_list.load_archive()?,
+        false => todos_list,
+    };
+
+    let matcher = match regex {
+        true => Matcher::regex(query, ignore_case)?,
+        false => Matcher::substring(query, ignore_case),
+    };
+
+    let hits = search::search(&todos_list.todos, &matcher);
+
+    if hits.is_empty() {
+        println!("There are no matching todos!");
+    } else {
+        let options = archive_display(DisplayOptions::default(), archive);
+
+        println!("{}", search::render(&hits, &options));
+    }
+
+    Ok(())
+}
+
+fn apply_completion_flags(todos_list: &mut TodoList, args: &[String]) -> Result<Vec<String>> {
+    //! Applies --cascade and --auto-parents, returning the remaining arguments.
+    for flag in args.iter().filter(|a| a.starts_with("--")) {
+        match flag.as_str() {
+            "--cascade" => todos_list.policy.cascade = true,
+            "--auto-parents" => todos_list.policy.auto_complete_parents = true,
+            _ => return Err(WhatodoError::InvalidCommand),
+        }
+    }
+
+    Ok(args
+        .iter()
+        .filter(|a| !a.starts_with("--"))
+        .cloned()
+        .collect())
+}
+
+fn complete_todo(mut todos_list: TodoList, mut args: Vec<String>) -> Result<()> {
+    let filter = take_filter(&mut args)?;
+    let args = apply_completion_flags(&mut todos_list, &args)?;
+
+    match filter {
+        Some(_) if !args.is_empty() => return Err(WhatodoError::InvalidCommand),
+        Some(filter) => {
+            let completed = todos_list.complete_where(&filter)?;
+
+            println!("Completed {completed} todos");
+        }
+        None => {
+            let items = selected_items(&todos_list, &args)?;
+            let completed = todos_list.complete_all(&items)?;
+
+            if completed != 1 {
+                println!("Completed {completed} todos");
             }
         }
     }
 
+    todos_list.save()
+}
+
+fn uncomplete_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let args = apply_completion_flags(&mut todos_list, &args)?;
+    let item = single_item(&todos_list, &args)?;
+
+    todos_list.uncomplete(&item)?;
+
+    todos_list.save()
+}
+
+fn toggle_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let args = apply_completion_flags(&mut todos_list, &args)?;
+    let item = single_item(&todos_list, &args)?;
+
+    todos_list.toggle(&item)?;
+
+    todos_list.save()
+}
+
+fn archive_done(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    if !args.is_empty() {
+        return Err(WhatodoError::InvalidCommand);
+    }
+
+    let mut archive = todos_list.load_archive()?;
+    let archived = todos_list.archive_done(&mut archive);
+
+    // Saving the archive first means a failure in between leaves todos in both, never neither
+    archive.save()?;
+    todos_list.save()?;
+
+    println!("Archived {archived} todos");
+
     Ok(())
 }
 
+fn unarchive_todo(mut todos_list: TodoList, args: Vec<String>) -> Result<()> {
+    let id = match args.as_slice() {
+        [id] => id.strip_prefix('#').unwrap_or(id),
+        [] => return Err(WhatodoError::NotEnoughArguments),
+        _ => return Err(WhatodoError::InvalidCommand),
+    };
+
+    let mut archive = todos_list.load_archive()?;
+    todos_list.unarchive(&mut archive, id)?;
+
+    todos_list.save()?;
+    archive.save()
+}
+
+fn remove_from_list(mut todos_list: TodoList, mut args: Vec<String>) -> Result<()> {
+    if let Some(filter) = take_filter(&mut args)? {
+        if !args.is_empty() {
+            return Err(WhatodoError::InvalidCommand);
+        }
+
+        let removed = todos_list.remove_where(&filter);
+
+        println!("Removed {removed} todos");
+
+        return todos_list.save();
+    }
+
+    match args.first().map(String::as_str) {
+        Some("all") => todos_list.clear(),
+        Some("done") => todos_list.remove_done(),
+        Some("todo") => todos_list.remove_todo(),
+        // Otherwise they are paths, ranges or ids, all resolved before anything is removed
+        _ => {
+            let items = selected_items(&todos_list, &args)?;
+            let removed = todos_list.remove_all(&items)?;
+
+            if removed != 1 {
+                println!("Removed {removed} todos");
+            }
+        }
+    }
+
+    todos_list.save()
+}
+
 fn help() {
     println!("usage: whatodo <command> [<args>]");
     println!();
-    println!("\tCreate a new todo list in current working directory:");
+    println!("\tCreate a new todo list in current working directory, unless there already is one:");
     println!();
     println!("\t\twhatodo init");
     println!();
+    println!("\tItems are given by their path, the indices of the item and the items it is nested");
+    println!("\tunder separated by dots (2.1 is the first sub item of the second item). Negative");
+    println!("\tindices count from the end (-1 is the last item, 2.-1 the last sub item of the");
+    println!("\tsecond item). An item's id can be given in place of its path.");
+    println!();
     println!("\tAdd item to todo list in current working directory:");
     println!();
     println!("\t\twhatodo add 'Description of todo item here'");
     println!();
     println!("\tAdd sub item to a todo list in current working directory:");
     println!();
-    println!("\t\twhatodo add [indices of todos] 'Description of todo item here'");
+    println!("\t\twhatodo add <path> 'Description of todo item here'");
+    println!();
+    println!("\tAdd item with a priority, or change the priority of an item (A, B, C or none):");
+    println!();
+    println!("\t\twhatodo add --priority A <path> 'Description of todo item here'");
+    println!("\t\twhatodo prioritize <path> (A|B|C|none)");
+    println!();
+    println!("\tAdd item with a due date, or change the due date of an item (YYYY-MM-DD, today,");
+    println!("\ttomorrow, a day of the week or none):");
+    println!();
+    println!("\t\twhatodo add --due tomorrow <path> 'Description of todo item here'");
+    println!("\t\twhatodo due <path> 2026-11-01");
+    println!();
+    println!("\tAdd item with tags and contexts, written in the description or given as flags:");
+    println!();
+    println!("\t\twhatodo add 'Fix login +backend @office'");
+    println!("\t\twhatodo add --tag backend --context office 'Fix login'");
     println!();
     println!("\tComplete an item on todo list:");
     println!();
-    println!("\t\twhatodo complete (number of the item you would like to complete)");
+    println!("\t\twhatodo complete <path>");
     println!();
-    println!("\tRemove an item from the todo list:");
+    println!("\tComplete or remove several items at once, given as paths, ranges of items in the");
+    println!("\tsame list such as 2-6 or 1.2-4, or every sub item of an item such as 2.*:");
+    println!();
+    println!("\t\twhatodo complete 1 3 5");
+    println!("\t\twhatodo remove 2-6 4.*");
+    println!();
+    println!("\tMark a completed item as not complete, or flip whether it is complete:");
+    println!();
+    println!("\t\twhatodo uncomplete <path>");
+    println!("\t\twhatodo toggle <path>");
+    println!();
+    println!("\tComplete an item along with all of its sub items, or complete parents once all");
+    println!("\tof their sub items are complete (set WHATODO_CASCADE or");
+    println!("\tWHATODO_AUTO_COMPLETE_PARENTS to always do so):");
     println!();
-    println!("\t\twhatodo remove (number of the item you would like to complete)");
+    println!("\t\twhatodo complete <path> --cascade");
+    println!("\t\twhatodo complete <path> --auto-parents");
     println!();
-    println!("\tRemove a sub item from the todo list:");
+    println!("\tChange the text of an item, in your editor when no text is given:");
     println!();
-    println!("\t\twhatodo remove [indices of todos] subtodo");
+    println!("\t\twhatodo edit <path> 'New description of todo item'");
+    println!("\t\twhatodo edit <path>");
     println!();
-    println!("\tDisplay items in todo list:");
+    println!("\tWrite notes for an item in your editor, or read them from stdin with -. Empty");
+    println!("\tnotes are removed:");
+    println!();
+    println!("\t\twhatodo note <path>");
+    println!("\t\techo 'Done when the tests pass' | whatodo note <path> -");
+    println!();
+    println!("\tDisplay everything about an item, notes included:");
+    println!();
+    println!("\t\twhatodo show <path>");
+    println!();
+    println!("\tMove an item, along with its sub items, to another place in the list:");
+    println!();
+    println!("\t\twhatodo move <path> to <new path>");
+    println!();
+    println!("\tMove an item within its own list:");
+    println!();
+    println!("\t\twhatodo (up|down|top|bottom) <path>");
+    println!();
+    println!("\tRemove an item from the todo list:");
+    println!();
+    println!("\t\twhatodo remove <path>");
+    println!();
+    println!("\tDisplay items in todo list, each with its path. done and todo show matching");
+    println!("\titems at any depth along with the items they are nested under:");
     println!();
     println!("\t\twhatodo checkout (all|done|todo)");
+    println!();
+    println!("\tRemove every item done, or every item not done, at any depth. Items still");
+    println!("\tholding sub items of the other kind are kept:");
+    println!();
+    println!("\t\twhatodo remove (done|todo)");
+    println!();
+    println!("\tDisplay items not done which are past due, due today, or due later:");
+    println!();
+    println!("\t\twhatodo checkout (overdue|today|upcoming)");
+    println!();
+    println!("\tDisplay items sorted by priority, or only items of one priority:");
+    println!();
+    println!("\t\twhatodo checkout (all|done|todo) --sort priority");
+    println!("\t\twhatodo checkout (all|done|todo) --priority (A|B|C)");
+    println!();
+    println!("\tDisplay items with a tag or context, along with the items they are nested under:");
+    println!();
+    println!("\t\twhatodo checkout (all|done|todo) --tag backend");
+    println!("\t\twhatodo checkout (all|done|todo) --context office");
+    println!();
+    println!("\tDisplay items along with their ids, an id can be given in place of the path of");
+    println!("\tan item to any command:");
+    println!();
+    println!("\t\twhatodo checkout (all|done|todo) --ids");
+    println!("\t\twhatodo complete k3x9a");
+    println!();
+    println!("\tDisplay items along with how long ago they were added, or completed:");
+    println!();
+    println!("\t\twhatodo checkout (all|done|todo) --ages");
+    println!();
+    println!("\tDisplay items completed within a range of days (YYYY-MM-DD, today, yesterday or a");
+    println!("\tday of the week):");
+    println!();
+    println!("\t\twhatodo checkout done --since 2026-10-01 --until yesterday");
+    println!();
+    println!("\tFind items by their text or notes, ignoring case with -i, or by a regular");
+    println!("\texpression with --regex:");
+    println!();
+    println!("\t\twhatodo search [-i] [--regex] [--] 'login'");
+    println!();
+    println!("\tDisplay, complete or remove every item matching a filter. Conditions are");
+    println!("\tstatus:(open|done|partial), tag:, ctx:, priority:, text:, id: and due, created or");
+    println!("\tdone compared with :, <, <=, > or >= to a date, or a number of days or weeks");
+    println!("\tfrom today such as 7d or -2w, combined with and, or, not and parentheses:");
+    println!();
+    println!("\t\twhatodo checkout --where 'status:open and (tag:api or priority:A) and due<7d'");
+    println!("\t\twhatodo complete --where 'tag:release'");
+    println!("\t\twhatodo remove --where 'status:done and done<-30d'");
+    println!();
+    println!("\tMove completed items, along with their sub items, to todo.archive next to the");
+    println!("\tlist, display or search archived items, or move an archived item back by its id:");
+    println!();
+    println!("\t\twhatodo archive");
+    println!("\t\twhatodo checkout archive");
+    println!("\t\twhatodo search --archive 'login'");
+    println!("\t\twhatodo unarchive k3x9a");
+    println!();
+    println!("\tRevert the latest changes to the list, or make undone changes again, with the");
+    println!("\tnumber of changes to step over. Changes are kept in todo.todos.history:");
+    println!();
+    println!("\t\twhatodo undo [count]");
+    println!("\t\twhatodo redo [count]");
+    println!("\t\twhatodo history");
+    println!();
+    println!("\tDisplay items in todo list together with the global list:");
+    println!();
+    println!("\t\twhatodo checkout (all|done|todo) --with-global");
+    println!();
+    println!("\tThe nearest todo.todos in the current directory or its parents is used, to use");
+    println!("\tanother list set WHATODO_FILE or pass it before the command:");
+    println!();
+    println!("\t\twhatodo --file path/to/list.todos <command> [<args>]");
+    println!();
+    println!("\tUse the global list kept in your data directory instead:");
+    println!();
+    println!("\t\twhatodo --global <command> [<args>]");
+}
+
+fn describe_command(command: &str, args: &[String]) -> String {
+    //! The command line as it could be typed again, recorded in the history of the list
+    let mut words = vec![command.to_string()];
+
+    for arg in args {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_.,:=+@#/".contains(c));
+
+        match (plain, arg.contains('\'')) {
+            (true, _) => words.push(arg.clone()),
+            (false, false) => words.push(format!("'{arg}'")),
+            // Within double quotes only these are special to the shell
+            (false, true) => words.push(format!(
+                "\"{}\"",
+                arg.chars().fold(String::new(), |mut quoted, c| {
+                    if "\"\\$`".contains(c) {
+                        quoted.push('\\');
+                    }
+                    quoted.push(c);
+                    quoted
+                })
+            )),
+        }
+    }
+
+    words.join(" ")
+}
+
+fn run<I: Iterator<Item = String>>(options: Options, command: String, args: I) -> Result<()> {
+    let args: Vec<String> = args.collect();
+    let action = describe_command(&command, &args);
+
+    // Every change made by the command is recorded in the history of the list
+    let load = || {
+        load_todos(&options).map(|mut todos_list| {
+            todos_list.action = Some(action.clone());
+            todos_list
+        })
+    };
+
+    match command.as_str() {
+        "init" => init_new_list(&options),
+        "add" => load().and_then(|todos| add_to_list(todos, args)),
+        "remove" => load().and_then(|todos| remove_from_list(todos, args)),
+        "complete" => load().and_then(|todos| complete_todo(todos, args)),
+        "move" => load().and_then(|todos| move_todo(todos, args)),
+        "up" | "down" | "top" | "bottom" => {
+            load().and_then(|todos| reorder_todo(todos, &command, args))
+        }
+        "prioritize" => load().and_then(|todos| prioritize_todo(todos, args)),
+        "due" => load().and_then(|todos| set_due_date(todos, args)),
+        "edit" => load().and_then(|todos| edit_todo(todos, args)),
+        "note" => load().and_then(|todos| write_notes(todos, args)),
+        "show" => load().and_then(|todos| show_todo(todos, args)),
+        "uncomplete" => load().and_then(|todos| uncomplete_todo(todos, args)),
+        "toggle" => load().and_then(|todos| toggle_todo(todos, args)),
+        "search" => load().and_then(|todos| search_list(todos, args)),
+        "archive" => load().and_then(|todos| archive_done(todos, args)),
+        "unarchive" => load().and_then(|todos| unarchive_todo(todos, args)),
+        "undo" => load().and_then(|todos| step_history(todos, args, false)),
+        "redo" => load().and_then(|todos| step_history(todos, args, true)),
+        "history" => load().and_then(print_history),
+        "checkout" => load().and_then(|todos| checkout_list(todos, args)),
+        "help" => {
+            help();
+            Ok(())
+        }
+        _ => Err(WhatodoError::InvalidCommand),
+    }
 }
 
 fn main() {
-    let mut args = env::args().skip(1);
+    let mut args = env::args().skip(1).peekable();
+
+    let options = match parse_options(&mut args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n");
+            help();
+            return;
+        }
+    };
 
     if let Some(command) = args.next() {
-        match match command.as_str() {
-            "init" => init_new_list(),
-            "add" => add_to_list(load_todos().unwrap(), args.collect()),
-            "remove" => remove_from_list(load_todos().unwrap(), args.collect()),
-            "complete" => complete_todo(load_todos().unwrap(), args.collect()),
-            "checkout" => checkout_list(
-                load_todos().unwrap(),
-                match args.next() {
-                    Some(arg) => arg,
-                    None => "all".to_string(),
-                },
-            ),
-            "help" => {
-                help();
-                Ok(())
-            }
-            _ => Err(WhatodoError::InvalidCommand),
-        } {
+        match run(options, command, args) {
             Ok(_) => (),
             Err(e) => {
                 eprintln!("{e}\n");
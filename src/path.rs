@@ -0,0 +1,286 @@
+use std::{fmt, str::FromStr};
+
+use crate::{error::WhatodoError, todo::Todo};
+
+/// One step of a TodoPath
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathIndex {
+    /// 0 indexed position from the start of the list
+    FromStart(usize),
+    /// Position counted from the end of the list, 1 being the last todo
+    FromEnd(usize),
+}
+
+/// Where a todo is, as typed by users: 1 indexed and dot separated, e.g. 2.1.3 is the third sub
+/// todo of the first sub todo of the second todo. Negative indices count from the end of their
+/// list, so -1 is the last todo and 2.-1 the last sub todo of the second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoPath(Vec<PathIndex>);
+
+impl TodoPath {
+    pub fn from_indices(indices: &[usize]) -> Self {
+        //! The path of 0 indexed indices, as used throughout the library
+        Self(
+            indices
+                .iter()
+                .map(|ind| PathIndex::FromStart(*ind))
+                .collect(),
+        )
+    }
+
+    pub fn indices(&self) -> &[PathIndex] {
+        &self.0
+    }
+
+    pub fn resolve(&self, todos: &[Todo]) -> Option<Vec<usize>> {
+        //! The 0 indexed path into todos. Every todo the path passes through must exist. The last
+        //! index, when counted from the start, is not checked so the path can name a place for a
+        //! todo to be added or moved to.
+        let mut curr_root = todos;
+        let mut resolved = Vec::with_capacity(self.0.len());
+
+        for (depth, index) in self.0.iter().enumerate() {
+            let ind = match *index {
+                PathIndex::FromStart(ind) => ind,
+                PathIndex::FromEnd(back) => curr_root.len().checked_sub(back)?,
+            };
+
+            resolved.push(ind);
+
+            if depth + 1 < self.0.len() {
+                curr_root = &curr_root.get(ind)?.sub_todos;
+            }
+        }
+
+        Some(resolved)
+    }
+}
+
+/// One or more todos given by a user: a single path, a range of siblings such as 2-6 or 1.2-4,
+/// or every sub todo of a todo such as 2.* (a lone * is every top level todo).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    Path(TodoPath),
+    /// The path of the parent along with the 0 indexed, inclusive ends of the range
+    Range(TodoPath, usize, usize),
+    Children(TodoPath),
+}
+
+fn siblings<'a>(todos: &'a [Todo], parent: &[usize]) -> Option<&'a [Todo]> {
+    let mut curr_root = todos;
+
+    for ind in parent {
+        curr_root = &curr_root.get(*ind)?.sub_todos;
+    }
+
+    Some(curr_root)
+}
+
+impl Selection {
+    pub fn resolve(&self, todos: &[Todo]) -> Option<Vec<Vec<usize>>> {
+        //! The 0 indexed paths of every todo selected, in order. Unlike TodoPath::resolve every
+        //! todo selected must exist.
+        let parent = match self {
+            Self::Path(path) => {
+                let resolved = path.resolve(todos)?;
+                let (last, parent) = resolved.split_last()?;
+
+                siblings(todos, parent)?.get(*last)?;
+
+                return Some(vec![resolved]);
+            }
+            Self::Range(parent, ..) | Self::Children(parent) => match parent.0.is_empty() {
+                true => Vec::new(),
+                false => Self::Path(parent.clone()).resolve(todos)?.remove(0),
+            },
+        };
+        let count = siblings(todos, &parent)?.len();
+
+        let indices = match self {
+            Self::Range(_, start, end) if *end < count => *start..*end + 1,
+            Self::Range(..) => return None,
+            _ => 0..count,
+        };
+
+        Some(
+            indices
+                .map(|ind| {
+                    let mut path = parent.clone();
+                    path.push(ind);
+                    path
+                })
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for Selection {
+    type Err = WhatodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || WhatodoError::InvalidPath(s.to_string());
+
+        let (parent, last) = match s.rsplit_once('.') {
+            Some((parent, last)) => (parent.parse()?, last),
+            None => (TodoPath(Vec::new()), s),
+        };
+
+        if last == "*" {
+            return Ok(Self::Children(parent));
+        }
+
+        // A range has a number before its dash, a lone leading dash is a negative index
+        match last.split_once('-') {
+            Some((start, end)) if !start.is_empty() => {
+                match (start.parse::<usize>(), end.parse::<usize>()) {
+                    (Ok(start), Ok(end)) if 0 < start && start <= end => {
+                        Ok(Self::Range(parent, start - 1, end - 1))
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+            _ => s.parse().map(Self::Path),
+        }
+    }
+}
+
+impl FromStr for TodoPath {
+    type Err = WhatodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || WhatodoError::InvalidPath(s.to_string());
+
+        s.split('.')
+            .map(|part| {
+                let (from_end, number) = match part.strip_prefix('-') {
+                    Some(number) => (true, number),
+                    None => (false, part),
+                };
+
+                // Indices start at 1 from either end, -0 and 0 name nothing
+                match (from_end, number.parse::<usize>()) {
+                    (_, Ok(0)) | (_, Err(_)) => Err(invalid()),
+                    (true, Ok(back)) => Ok(PathIndex::FromEnd(back)),
+                    (false, Ok(ind)) => Ok(PathIndex::FromStart(ind - 1)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for TodoPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|index| match index {
+                PathIndex::FromStart(ind) => (ind + 1).to_string(),
+                PathIndex::FromEnd(back) => format!("-{back}"),
+            })
+            .collect();
+
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_todos() -> Vec<Todo> {
+        let mut first = Todo::new(None, "First".to_string());
+        first.sub_todos.push(Todo::new(None, "Sub".to_string()));
+        first
+            .sub_todos
+            .push(Todo::new(None, "Another sub".to_string()));
+
+        vec![first, Todo::new(None, "Second".to_string())]
+    }
+
+    fn path(s: &str) -> TodoPath {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(
+            vec![
+                PathIndex::FromStart(1),
+                PathIndex::FromStart(0),
+                PathIndex::FromEnd(1)
+            ],
+            path("2.1.-1").indices()
+        );
+        assert_eq!("2.1.-1", path("2.1.-1").to_string());
+        assert_eq!("1.2.10", TodoPath::from_indices(&[0, 1, 9]).to_string());
+
+        for invalid in ["", "0", "1.", ".1", "1..2", "-0", "--1", "1 2", "a", "1.b"] {
+            assert!(
+                matches!(
+                    invalid.parse::<TodoPath>(),
+                    Err(WhatodoError::InvalidPath(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_negative_indices() {
+        let todos = example_todos();
+
+        assert_eq!(Some(vec![1]), path("-1").resolve(&todos));
+        assert_eq!(Some(vec![0, 1]), path("-2.-1").resolve(&todos));
+        assert_eq!(Some(vec![0, 0]), path("1.-2").resolve(&todos));
+        assert_eq!(None, path("1.-3").resolve(&todos));
+        assert_eq!(None, path("-3").resolve(&todos));
+    }
+
+    #[test]
+    fn parse_selections() {
+        let selection = |s: &str| s.parse::<Selection>().unwrap();
+
+        assert_eq!(Selection::Path(path("2.-1")), selection("2.-1"));
+        assert_eq!(
+            Selection::Range(TodoPath::from_indices(&[]), 1, 5),
+            selection("2-6")
+        );
+        assert_eq!(Selection::Range(path("1"), 1, 3), selection("1.2-4"));
+        assert_eq!(Selection::Children(path("2")), selection("2.*"));
+        assert_eq!(
+            Selection::Children(TodoPath::from_indices(&[])),
+            selection("*")
+        );
+
+        for invalid in ["0-2", "3-2", "2-", "1-a", "*.1", "2.**", ".*", "-1-2"] {
+            assert!(invalid.parse::<Selection>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn resolve_selections() {
+        let todos = example_todos();
+        let resolve = |s: &str| s.parse::<Selection>().unwrap().resolve(&todos);
+
+        assert_eq!(Some(vec![vec![0, 1]]), resolve("1.-1"));
+        assert_eq!(Some(vec![vec![0], vec![1]]), resolve("1-2"));
+        assert_eq!(Some(vec![vec![0, 0], vec![0, 1]]), resolve("1.*"));
+        assert_eq!(Some(vec![]), resolve("2.*"));
+        assert_eq!(Some(vec![vec![0], vec![1]]), resolve("*"));
+
+        // Selected todos must exist
+        assert_eq!(None, resolve("1.3"));
+        assert_eq!(None, resolve("1.2-3"));
+        assert_eq!(None, resolve("3.*"));
+    }
+
+    #[test]
+    fn resolve_leaves_last_index_unchecked() {
+        let todos = example_todos();
+
+        assert_eq!(Some(vec![0, 2]), path("1.3").resolve(&todos));
+        assert_eq!(Some(vec![1, 0]), path("2.1").resolve(&todos));
+        assert_eq!(None, path("3.1").resolve(&todos));
+    }
+}
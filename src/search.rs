@@ -0,0 +1,195 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    error::WhatodoError,
+    path::TodoPath,
+    todo::{DisplayOptions, Todo},
+};
+
+type Result<T> = std::result::Result<T, WhatodoError>;
+
+/// What a search looks for in the contents and notes of each todo
+#[derive(Debug)]
+pub enum Matcher {
+    Substring(String),
+    /// Holds the query already lowercased
+    IgnoreCase(String),
+    Pattern(Regex),
+}
+
+impl Matcher {
+    pub fn substring(query: &str, ignore_case: bool) -> Self {
+        match ignore_case {
+            true => Self::IgnoreCase(query.to_lowercase()),
+            false => Self::Substring(query.to_string()),
+        }
+    }
+
+    pub fn regex(query: &str, ignore_case: bool) -> Result<Self> {
+        RegexBuilder::new(query)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(Self::Pattern)
+            .map_err(WhatodoError::InvalidPattern)
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(query) => text.contains(query.as_str()),
+            Self::IgnoreCase(query) => text.to_lowercase().contains(query.as_str()),
+            Self::Pattern(regex) => regex.is_match(text),
+        }
+    }
+
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.is_match(&todo.contents) || todo.notes.as_deref().is_some_and(|n| self.is_match(n))
+    }
+}
+
+/// A todo found by a search, along with where it lives in the tree
+#[derive(Debug)]
+pub struct SearchHit<'a> {
+    /// 0 indexed path of the todo in its list
+    pub path: Vec<usize>,
+    pub todo: &'a Todo,
+    /// The todos above this one, outermost first
+    pub ancestors: Vec<&'a Todo>,
+}
+
+fn search_into<'a>(
+    todos: &'a [Todo],
+    matcher: &Matcher,
+    parent: &[usize],
+    ancestors: &mut Vec<&'a Todo>,
+    hits: &mut Vec<SearchHit<'a>>,
+) {
+    for (ind, todo) in todos.iter().enumerate() {
+        let mut path = parent.to_vec();
+        path.push(ind);
+
+        if matcher.matches(todo) {
+            hits.push(SearchHit {
+                path: path.clone(),
+                todo,
+                ancestors: ancestors.clone(),
+            });
+        }
+
+        ancestors.push(todo);
+        search_into(&todo.sub_todos, matcher, &path, ancestors, hits);
+        ancestors.pop();
+    }
+}
+
+pub fn search<'a>(todos: &'a [Todo], matcher: &Matcher) -> Vec<SearchHit<'a>> {
+    //! Every todo matched, at any depth, in the order they are stored
+    let mut hits = Vec::new();
+
+    search_into(todos, matcher, &[], &mut Vec::new(), &mut hits);
+
+    hits
+}
+
+pub fn render(hits: &[SearchHit], options: &DisplayOptions) -> String {
+    //! One line per hit starting with its dotted path, followed by the todos it is nested under
+    let mut lines = Vec::new();
+
+    for hit in hits {
+        lines.push(format!(
+            "{} [{}] - {}",
+            TodoPath::from_indices(&hit.path),
+            hit.todo.marker(),
+            hit.todo.label_with(options)
+        ));
+
+        if !hit.ancestors.is_empty() {
+            let breadcrumbs: Vec<&str> =
+                hit.ancestors.iter().map(|t| t.contents.as_str()).collect();
+
+            lines.push(format!("    in {}", breadcrumbs.join(" > ")));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_todos() -> Vec<Todo> {
+        let mut backend = Todo::new(None, "Backend".to_string());
+        backend.sub_todos.push(Todo::new(None, "Auth".to_string()));
+        backend.sub_todos[0]
+            .sub_todos
+            .push(Todo::new(None, "Fix login".to_string()));
+        backend.sub_todos[0]
+            .sub_todos
+            .push(Todo::new(Some(true), "Log out".to_string()));
+
+        let mut docs = Todo::new(None, "Docs".to_string());
+        docs.notes = Some("Explain the LOGIN flow".to_string());
+
+        vec![backend, docs]
+    }
+
+    fn paths(hits: &[SearchHit]) -> Vec<String> {
+        hits.iter()
+            .map(|h| TodoPath::from_indices(&h.path).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn substring_search() {
+        let todos = example_todos();
+
+        assert_eq!(
+            vec!["1.1.1"],
+            paths(&search(&todos, &Matcher::substring("login", false)))
+        );
+        assert_eq!(
+            vec!["1.1.1", "2"],
+            paths(&search(&todos, &Matcher::substring("LogIn", true)))
+        );
+        assert!(search(&todos, &Matcher::substring("nothing", true)).is_empty());
+    }
+
+    #[test]
+    fn regex_search() {
+        let todos = example_todos();
+
+        assert_eq!(
+            vec!["1.1.1", "1.1.2"],
+            paths(&search(
+                &todos,
+                &Matcher::regex("^(Fix|Log) ", false).unwrap()
+            ))
+        );
+        assert!(matches!(
+            Matcher::regex("(", false),
+            Err(WhatodoError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn render_w_breadcrumbs() {
+        let todos = example_todos();
+        let hits = search(&todos, &Matcher::substring("log", true));
+
+        assert_eq!(
+            "1.1.1 [ ] - Fix login\n    in Backend > Auth\n1.1.2 [X] - Log out\n    in Backend > Auth\n2 [ ] - Docs",
+            render(&hits, &DisplayOptions::default())
+        );
+
+        let mut todos = example_todos();
+        todos[1].id = Some("k3x9a".to_string());
+        let hits = search(&todos, &Matcher::substring("docs", true));
+
+        let options = DisplayOptions {
+            ids: true,
+            ..Default::default()
+        };
+
+        assert_eq!("2 [ ] - Docs #k3x9a", render(&hits, &options));
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::WhatodoError,
+    todo::{self, Todo},
+};
+
+type Result<T> = std::result::Result<T, WhatodoError>;
+
+pub const TODOS_FILE_NAME: &str = "todo.todos";
+
+pub fn global_todos_path() -> Option<PathBuf> {
+    //! Location of the user level list, $XDG_DATA_HOME/whatodo/todo.todos, falling back to
+    //! ~/.local/share/whatodo/todo.todos when XDG_DATA_HOME is not set.
+    let data_home = match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME").filter(|d| !d.is_empty())?)
+            .join(".local")
+            .join("share"),
+    };
+
+    Some(data_home.join("whatodo").join(TODOS_FILE_NAME))
+}
+
+pub fn find_todos_file(start: &Path) -> Option<PathBuf> {
+    //! Searches start and then each of its parents for a todo.todos file, the same way git looks
+    //! for its repository. The search stops at the root of a repository (a directory containing
+    //! .git) so lists from enclosing projects are never picked up by accident.
+    for dir in start.ancestors() {
+        let candidate = dir.join(TODOS_FILE_NAME);
+
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+
+        if dir.join(".git").exists() {
+            return None;
+        }
+    }
+
+    None
+}
+
+pub fn load_todos(path: &Path) -> Result<Vec<Todo>> {
+    let mut f = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(WhatodoError::CannotLoadTodos(e));
+        }
+    };
+
+    let mut todo_string = String::new();
+
+    match f.read_to_string(&mut todo_string) {
+        // Get all of the todos from the file
+        Ok(_) => (),
+        Err(e) => {
+            return Err(WhatodoError::CannotLoadTodos(e));
+        }
+    }
+
+    // Loads todos read in from file
+    todo::from_todos_file_string(&todo_string).map_err(WhatodoError::CannotParseTodos)
+}
+
+pub fn init_todos(path: &Path) -> Result<()> {
+    //! Creates an empty list at path. An existing list is never emptied, as doing so would not be
+    //! recorded in its history.
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(WhatodoError::CannotInitTodos)?;
+    }
+
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(WhatodoError::CannotInitTodos(e)),
+    }
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    //! Path of the copy of the previous version kept by save_todos, e.g. todo.todos.bak
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+
+    PathBuf::from(backup)
+}
+
+pub fn archive_path(path: &Path) -> PathBuf {
+    //! Path of the list completed todos are archived to, e.g. todo.archive for todo.todos
+    path.with_extension("archive")
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    // The temporary file must be in the same directory, otherwise the rename is not atomic
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(format!(".{}.tmp", std::process::id()));
+
+    path.with_file_name(file_name)
+}
+
+pub fn write_atomic(path: &Path, contents: &str, backup: bool) -> std::io::Result<()> {
+    //! Writes contents to a temporary file next to path, flushes it to disk and renames it over
+    //! path, so a crash at any point leaves either the old or the new version in place. When
+    //! backup is set, the version being replaced is copied to backup_path first.
+    let tmp = temp_path(path);
+
+    let result = (|| {
+        let mut otf = File::create(&tmp)?;
+        otf.write_all(contents.as_bytes())?;
+        otf.sync_all()?;
+
+        if backup && path.exists() {
+            fs::copy(path, backup_path(path))?;
+        }
+
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    // Make the rename itself durable, not every platform allows opening a directory
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+
+        if let Ok(d) = File::open(dir) {
+            let _ = d.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save_todos(path: &Path, todos_list: &[Todo], backup: bool) -> Result<()> {
+    write_atomic(path, &todo::to_todos_file_string(todos_list), backup)
+        .map_err(WhatodoError::CannotSaveTodos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_todos_file_in_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("src").join("deeper");
+
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join(TODOS_FILE_NAME), "").unwrap();
+
+        assert_eq!(
+            Some(dir.path().join(TODOS_FILE_NAME)),
+            find_todos_file(&nested)
+        );
+    }
+
+    #[test]
+    fn find_todos_file_stops_at_repository_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src");
+
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join(TODOS_FILE_NAME), "").unwrap();
+
+        assert_eq!(None, find_todos_file(&nested));
+
+        fs::write(repo.join(TODOS_FILE_NAME), "").unwrap();
+
+        assert_eq!(Some(repo.join(TODOS_FILE_NAME)), find_todos_file(&nested));
+    }
+
+    #[test]
+    fn init_todos_keeps_existing_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("todo.todos");
+
+        init_todos(&path).unwrap();
+        assert_eq!("", fs::read_to_string(&path).unwrap());
+
+        fs::write(&path, "0|Keep me|\n").unwrap();
+
+        assert!(matches!(
+            init_todos(&path),
+            Err(WhatodoError::CannotInitTodos(_))
+        ));
+        assert_eq!("0|Keep me|\n", fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn save_todos_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        fs::write(&path, "0|Old|\n").unwrap();
+
+        save_todos(
+            &path,
+            &[Todo {
+                contents: "New".to_string(),
+                ..Default::default()
+            }],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!("#whatodo 2\n0|New|\n", fs::read_to_string(&path).unwrap());
+        assert!(!backup_path(&path).exists());
+        // Only the list itself is left behind
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn save_todos_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.todos");
+
+        fs::write(&path, "0|Old|\n").unwrap();
+
+        save_todos(
+            &path,
+            &[Todo {
+                contents: "New".to_string(),
+                ..Default::default()
+            }],
+            true,
+        )
+        .unwrap();
+
+        assert_eq!("0|Old|\n", fs::read_to_string(backup_path(&path)).unwrap());
+        assert_eq!("#whatodo 2\n0|New|\n", fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn save_todos_leaves_original_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("todo.todos");
+
+        assert!(save_todos(&path, &[], false).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -3,14 +3,88 @@
 // New line == new todo
 // Each field separated with |
 // sub_todos array begin represented by [ and end by ]
-// Separate todos in sub_todos array separated by ,
+// Separate todos in sub_todos array separated by %
+// Optional attributes follow the contents as key=value fields, e.g. 0|Something|p=A|
+// Since version 2, the characters | % [ ] \ and new lines are escaped with a backslash
 
-use std::fmt;
+use std::{collections::BTreeSet, fmt, str::FromStr};
 
-#[derive(Debug)]
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::{
+    date::{self, TIME_FORMAT},
+    error::ParseError,
+};
+
+/// First line of every file written in the escaped format, files without it are read as version 1
+pub const TODOS_HEADER: &str = "#whatodo 2";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatVersion {
+    /// Values are written as is, no escaping
+    V1,
+    /// Values are escaped with a backslash
+    V2,
+}
+
+/// Priority levels, A being the most important
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    A,
+    B,
+    C,
+}
+
+impl FromStr for Priority {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "A" | "1" => Ok(Self::A),
+            "B" | "2" => Ok(Self::B),
+            "C" | "3" => Ok(Self::C),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+            Self::C => write!(f, "C"),
+        }
+    }
+}
+
+/// Optional details included when listing todos
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayOptions {
+    pub ids: bool,
+    /// Show how long ago todos were added, or completed, as of this time
+    pub ages: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Todo {
+    /// Short identifier which stays the same wherever the todo is moved, see utils::generate_id
+    pub id: Option<String>,
     pub complete: bool,
     pub contents: String,
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDate>,
+    /// Written as +tag in todo text
+    pub tags: BTreeSet<String>,
+    /// Written as @context in todo text
+    pub contexts: BTreeSet<String>,
+    pub created: Option<NaiveDateTime>,
+    /// When the todo was last marked complete, only set while it is complete
+    pub completed: Option<NaiveDateTime>,
+    /// Free form text kept with the todo, may span several lines
+    pub notes: Option<String>,
     pub sub_todos: Vec<Todo>,
 }
 
@@ -18,35 +92,160 @@ impl Todo {
     pub fn new(complete: Option<bool>, contents: String) -> Self {
         // Takes an option to allow for loading from file
         Self {
-            complete: match complete {
-                Some(b) => b,
-                None => false,
-            },
+            complete: complete.unwrap_or_default(),
             contents,
-            sub_todos: Vec::new(),
+            created: Some(date::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_text(complete: Option<bool>, text: &str) -> Self {
+        //! Creates a todo from text as typed by a user, words starting with + and a letter are
+        //! taken as tags and words starting with @ and a letter as contexts rather than as part
+        //! of the contents. The rest of the text is kept as it was typed.
+        let mut todo = Self::new(complete, String::new());
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (word, after) = rest.split_at(word_end);
+            rest = after;
+
+            let mut chars = word.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some('+'), Some(first)) if first.is_alphabetic() => {
+                    todo.tags.insert(word[1..].to_string());
+                }
+                (Some('@'), Some(first)) if first.is_alphabetic() => {
+                    todo.contexts.insert(word[1..].to_string());
+                }
+                _ => {
+                    let space_end = rest
+                        .find(|c: char| !c.is_whitespace())
+                        .unwrap_or(rest.len());
+
+                    todo.contents.push_str(word);
+                    todo.contents.push_str(&rest[..space_end]);
+                    rest = &rest[space_end..];
+
+                    continue;
+                }
+            }
+
+            // The blanks after a tag go with it, or those before it when it ends a line
+            rest = rest.trim_start_matches([' ', '\t']);
+
+            if rest.is_empty() || rest.starts_with(['\n', '\r']) {
+                let kept = todo.contents.trim_end_matches([' ', '\t']).len();
+                todo.contents.truncate(kept);
+            }
+        }
+
+        todo
+    }
+
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        //! The optional fields which are set, in the order they are written to file
+        let mut attributes = Vec::new();
+
+        if let Some(id) = &self.id {
+            attributes.push(("id", id.clone()));
+        }
+
+        if let Some(priority) = self.priority {
+            attributes.push(("p", priority.to_string()));
+        }
+
+        if let Some(due) = self.due {
+            attributes.push(("due", due.format(DATE_FORMAT).to_string()));
+        }
+
+        for tag in self.tags.iter() {
+            attributes.push(("tag", tag.clone()));
+        }
+
+        for context in self.contexts.iter() {
+            attributes.push(("ctx", context.clone()));
+        }
+
+        if let Some(created) = self.created {
+            attributes.push(("created", created.format(TIME_FORMAT).to_string()));
+        }
+
+        if let Some(completed) = self.completed {
+            attributes.push(("done", completed.format(TIME_FORMAT).to_string()));
+        }
+
+        if let Some(notes) = &self.notes {
+            attributes.push(("note", notes.clone()));
+        }
+
+        attributes
+    }
+
+    fn set_attribute(&mut self, key: &str, value: &str) -> Result<(), &'static str> {
+        //! On failure returns a description of what was expected
+        match key {
+            "id" => {
+                self.id = Some(value.to_string());
+            }
+            "p" => {
+                self.priority = Some(value.parse().map_err(|_| "priority (A, B or C)")?);
+            }
+            "due" => {
+                self.due = Some(
+                    NaiveDate::parse_from_str(value, DATE_FORMAT)
+                        .map_err(|_| "due date (YYYY-MM-DD)")?,
+                );
+            }
+            "tag" => {
+                self.tags.insert(value.to_string());
+            }
+            "ctx" => {
+                self.contexts.insert(value.to_string());
+            }
+            "created" => {
+                self.created = Some(
+                    NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+                        .map_err(|_| "creation time (YYYY-MM-DDTHH:MM:SS)")?,
+                );
+            }
+            "done" => {
+                self.completed = Some(
+                    NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+                        .map_err(|_| "completion time (YYYY-MM-DDTHH:MM:SS)")?,
+                );
+            }
+            "note" => {
+                self.notes = Some(value.to_string());
+            }
+            _ => return Err("attribute (id, p, due, tag, ctx, created, done or note)"),
         }
+
+        Ok(())
     }
 
     pub fn to_todos(&self) -> String {
         // Generally used for serialization
-        if self.sub_todos.len() == 0 {
-            format!(
-                "{}|{}|",
-                match self.complete {
-                    true => 1,
-                    false => 0,
-                },
-                self.contents
-            )
+        let mut root = format!(
+            "{}|{}|",
+            match self.complete {
+                true => 1,
+                false => 0,
+            },
+            escape_value(&self.contents)
+        );
+
+        for (key, value) in self.attributes() {
+            root.push_str(&escape_value(&format!("{key}={value}")));
+            root.push('|');
+        }
+
+        if self.sub_todos.is_empty() {
+            root
         } else {
-            let mut root = format!(
-                "{}|{}|[",
-                match self.complete {
-                    true => 1,
-                    false => 0,
-                },
-                self.contents
-            );
+            root.push('[');
 
             let mut children = Vec::new();
 
@@ -62,104 +261,162 @@ impl Todo {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        if self.sub_todos.len() == 0 {
-            format!(
-                "[{}] - {}",
-                match self.complete {
-                    false => ' ',
-                    true => 'X',
-                },
-                self.contents
-            )
-        } else {
-            let mut res = vec![format!(
-                "[{}] - {}",
-                match self.complete {
-                    false => ' ',
-                    true => 'X',
-                },
-                self.contents
-            )];
+    pub fn mark_complete(&mut self, complete: bool) {
+        //! Sets complete, stamping the time it was completed. Completing a todo which is already
+        //! complete keeps the original time.
+        self.completed = match (complete, self.complete) {
+            (true, true) => self.completed,
+            (true, false) => Some(date::now()),
+            (false, _) => None,
+        };
+        self.complete = complete;
+    }
 
-            for child in self.sub_todos.iter() {
-                let child_string = child.to_string();
+    pub fn set_complete_recursive(&mut self, complete: bool) {
+        self.mark_complete(complete);
 
-                let complete = child_string
-                    .split("\n")
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join("\n- ");
+        for child in self.sub_todos.iter_mut() {
+            child.set_complete_recursive(complete);
+        }
+    }
 
-                res.push(format!("    {complete}"));
-            }
+    pub fn all_sub_todos_complete(&self) -> bool {
+        self.sub_todos.iter().all(|t| t.complete)
+    }
+
+    pub fn is_done(&self) -> bool {
+        //! Complete along with every todo below it
+        self.complete && self.sub_todos.iter().all(Todo::is_done)
+    }
+
+    pub fn is_partial(&self) -> bool {
+        //! An open todo with some completed work below it
+        !self.complete && self.sub_todos.iter().any(|t| t.complete || t.is_partial())
+    }
 
-            res.join("\n")
+    pub fn marker(&self) -> char {
+        match self.complete {
+            true => 'X',
+            false if self.is_partial() => '~',
+            false => ' ',
         }
     }
 
-    pub fn to_enumerated_string(&self, index: Option<usize>) -> String {
-        //! The enumeration for top level todos will be handled elsewhere,
-        //! for the subtodos, that will be handled by the index parameter.
+    pub fn label(&self) -> String {
+        self.label_with(&DisplayOptions::default())
+    }
 
-        if self.sub_todos.len() == 0 {
-            format!(
-                "{}[{}] - {}",
-                match index {
-                    Some(ind) => format!("{}. ", ind),
-                    None => "".to_string(),
-                },
-                match self.complete {
-                    false => ' ',
-                    true => 'X',
-                },
-                self.contents
-            )
-        } else {
-            let mut res = vec![format!(
-                "{}[{}] - {}",
-                match index {
-                    Some(ind) => format!("{}. ", ind),
-                    None => "".to_string(),
-                },
-                match self.complete {
-                    false => ' ',
-                    true => 'X',
-                },
-                self.contents
-            )];
+    pub fn label_with(&self, options: &DisplayOptions) -> String {
+        //! The contents together with the optional fields worth showing in a listing
+        let mut label = match self.priority {
+            Some(priority) => format!("({priority}) {}", self.contents),
+            None => self.contents.clone(),
+        };
 
-            for (child_index, child) in self.sub_todos.iter().enumerate() {
-                let child_string = child.to_enumerated_string(Some(child_index + 1));
+        for tag in self.tags.iter() {
+            label.push_str(&format!(" +{tag}"));
+        }
+
+        for context in self.contexts.iter() {
+            label.push_str(&format!(" @{context}"));
+        }
 
-                let complete = child_string
-                    .split("\n")
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join("\n- ");
+        if let Some(due) = self.due {
+            label.push_str(&format!(" due:{}", due.format(DATE_FORMAT)));
+        }
 
-                res.push(format!("    {complete}"));
+        if let Some(now) = options.ages {
+            match (self.completed, self.created) {
+                (Some(completed), _) if self.complete => {
+                    label.push_str(&format!(" (done {} ago)", date::format_age(completed, now)));
+                }
+                (_, Some(created)) => {
+                    label.push_str(&format!(" (added {} ago)", date::format_age(created, now)));
+                }
+                _ => (),
             }
+        }
 
-            res.join("\n")
+        if let (true, Some(id)) = (options.ids, &self.id) {
+            label.push_str(&format!(" #{id}"));
         }
+
+        label
+    }
+
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.complete && self.due.is_some_and(|due| due < today)
     }
-}
 
-impl PartialEq for Todo {
-    fn eq(&self, other: &Self) -> bool {
-        // The sub todos will be the same generally speaking
-        self.contents == other.contents
+    pub fn completed_between(&self, since: Option<NaiveDate>, until: Option<NaiveDate>) -> bool {
+        //! Whether the todo is complete and was completed within since and until, inclusive.
+        //! Todos completed before completion times were kept never match.
+        match (self.complete, self.completed) {
+            (true, Some(completed)) => {
+                since.is_none_or(|since| completed.date() >= since)
+                    && until.is_none_or(|until| completed.date() <= until)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn to_enumerated_string(&self, index: Option<usize>) -> String {
+        self.to_enumerated_string_with(index, &DisplayOptions::default())
+    }
+
+    pub fn to_enumerated_string_with(
+        &self,
+        index: Option<usize>,
+        options: &DisplayOptions,
+    ) -> String {
+        //! The enumeration for top level todos will be handled elsewhere,
+        //! for the subtodos, that will be handled by the index parameter.
+        //! Open todos with completed sub todos are marked [~].
+
+        let mut res = vec![format!(
+            "{}[{}] - {}",
+            match index {
+                Some(ind) => format!("{}. ", ind),
+                None => "".to_string(),
+            },
+            self.marker(),
+            self.label_with(options)
+        )];
+
+        for (child_index, child) in self.sub_todos.iter().enumerate() {
+            // Every line of a child is indented, so deeper todos are indented once per level
+            for line in child
+                .to_enumerated_string_with(Some(child_index + 1), options)
+                .lines()
+            {
+                res.push(format!("    {line}"));
+            }
+        }
+
+        res.join("\n")
     }
 }
 
 impl fmt::Display for Todo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.complete {
-            write!(f, "[X] - {}", self.contents)
-        } else {
-            write!(f, "[ ] - {}", self.contents)
+        write!(
+            f,
+            "[{}] - {}",
+            match self.complete {
+                false => ' ',
+                true => 'X',
+            },
+            self.label()
+        )?;
+
+        for child in self.sub_todos.iter() {
+            // Every line of a child is prefixed, so deeper todos pick up one marker per level
+            for line in child.to_string().lines() {
+                write!(f, "\n- {line}")?;
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -170,129 +427,309 @@ enum TodoTokens {
     TodoArrEnd,     // ]
     TodoSeparator,  // %
     TodoValue(String),
+    EndOfLine,
+}
+
+impl TodoTokens {
+    fn describe(&self) -> String {
+        match self {
+            Self::FieldSeparator => "|".to_string(),
+            Self::TodoArrBeg => "[".to_string(),
+            Self::TodoArrEnd => "]".to_string(),
+            Self::TodoSeparator => "%".to_string(),
+            Self::TodoValue(val) => format!("\"{val}\""),
+            Self::EndOfLine => "end of line".to_string(),
+        }
+    }
 }
 
-fn tokenize_todo_string(todo_str: &String) -> Vec<TodoTokens> {
-    let mut str_chars = todo_str.chars();
+// Each token is paired with the 1 indexed column it begins at
+type Token = (TodoTokens, usize);
 
-    let mut tokens: Vec<TodoTokens> = Vec::new();
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
 
-    // Get the complete value
-    tokens.push(TodoTokens::TodoValue(
-        str_chars.by_ref().take_while(|e| *e != '|').collect(),
-    ));
+    for c in value.chars() {
+        match c {
+            '\\' | '|' | '%' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn read_value<I: Iterator<Item = (usize, char)>>(
+    str_chars: &mut I,
+    version: FormatVersion,
+) -> (String, bool) {
+    //! Reads a single value up to and including the next unescaped bar, the bar is consumed
+    //! but not included in the value. The flag returned is false when the line ended before
+    //! a bar was found.
+    let mut value = String::new();
+
+    while let Some((_, c)) = str_chars.next() {
+        match c {
+            '|' => return (value, true),
+            '\\' if version == FormatVersion::V2 => match str_chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, escaped)) => value.push(escaped),
+                None => value.push('\\'),
+            },
+            _ => value.push(c),
+        }
+    }
+
+    (value, false)
+}
 
-    tokens.push(TodoTokens::FieldSeparator);
+fn tokenize_todo_string(todo_str: &str, version: FormatVersion) -> Vec<Token> {
+    let mut str_chars = todo_str
+        .chars()
+        .enumerate()
+        .map(|(i, c)| (i + 1, c))
+        .peekable();
+    let end_column = todo_str.chars().count() + 1;
 
-    // Get the contents value
-    tokens.push(TodoTokens::TodoValue(
-        str_chars.by_ref().take_while(|e| *e != '|').collect(),
-    ));
+    let mut tokens: Vec<Token> = Vec::new();
 
-    tokens.push(TodoTokens::FieldSeparator);
+    // Get the complete value and the contents value
+    for _ in 0..2 {
+        let column = str_chars.peek().map_or(end_column, |(i, _)| *i);
+        let (value, terminated) = read_value(&mut str_chars, version);
+
+        tokens.push((TodoTokens::TodoValue(value), column));
+
+        if !terminated {
+            tokens.push((TodoTokens::EndOfLine, end_column));
+            return tokens;
+        }
 
-    while let Some(c) = str_chars.by_ref().next() {
+        tokens.push((TodoTokens::FieldSeparator, column));
+    }
+
+    while let Some((column, c)) = str_chars.peek().copied() {
         match c {
-            '|' => tokens.push(TodoTokens::FieldSeparator), // If bar is encountered, push FieldSeparator, go to next
-            '%' => tokens.push(TodoTokens::TodoSeparator), // If percent sign is encountered, push TodoSeparator, go to next
-            '[' => tokens.push(TodoTokens::TodoArrBeg), // If open bracket is encountered, push TodoArrBeg, go to next
-            ']' => tokens.push(TodoTokens::TodoArrEnd), // If close bracket is encountered, push TodoArrEnd, go to next
+            '|' => tokens.push((TodoTokens::FieldSeparator, column)), // If bar is encountered, push FieldSeparator, go to next
+            '%' => tokens.push((TodoTokens::TodoSeparator, column)), // If percent sign is encountered, push TodoSeparator, go to next
+            '[' => tokens.push((TodoTokens::TodoArrBeg, column)), // If open bracket is encountered, push TodoArrBeg, go to next
+            ']' => tokens.push((TodoTokens::TodoArrEnd, column)), // If close bracket is encountered, push TodoArrEnd, go to next
             _ => {
                 // Any other found text will be some type of value, therefore collect it into a new string
-                let mut new_value = String::new();
-                new_value.push(c);
+                let (value, terminated) = read_value(&mut str_chars, version);
 
-                let rest_value = str_chars
-                    .by_ref()
-                    .take_while(|e| *e != '|')
-                    .collect::<String>();
+                tokens.push((TodoTokens::TodoValue(value), column));
 
-                new_value.push_str(&rest_value);
+                if terminated {
+                    tokens.push((TodoTokens::FieldSeparator, column));
+                }
 
-                tokens.push(TodoTokens::TodoValue(new_value));
-                tokens.push(TodoTokens::FieldSeparator);
+                continue;
             }
         }
+
+        str_chars.next();
     }
 
+    tokens.push((TodoTokens::EndOfLine, end_column));
+
     tokens
 }
 
-fn todo_from_tokens(tokens: Vec<TodoTokens>) -> Todo {
-    let num_tokens = tokens.iter().count();
-    let mut token_iter = tokens.iter();
+fn unexpected(token: &Token, expected: &str) -> ParseError {
+    ParseError {
+        line: 1,
+        column: token.1,
+        expected: expected.to_string(),
+        found: token.0.describe(),
+    }
+}
 
-    if num_tokens == 4 {
-        // Todo with no nesting
-        let mut todo = Todo::new(None, "".to_string());
+struct TodoParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
 
-        if let Some(TodoTokens::TodoValue(complete_field)) = token_iter.by_ref().next() {
-            todo.complete = match complete_field.as_str() {
-                "0" => false,
-                "1" => true,
-                _ => unreachable!(),
-            };
+// Grammar of a single line, parsed top down:
+//   line      := todo EndOfLine
+//   todo      := TodoValue | TodoValue | ( TodoValue | )* ( [ sub_todos ] )?
+//   sub_todos := ( todo ( % todo )* )?
+impl<'a> TodoParser<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        //! The tokens must end with EndOfLine, the parser never moves past it.
+        Self {
+            tokens,
+            position: 0,
         }
+    }
+
+    fn peek(&self) -> &'a Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> &'a Token {
+        let token = self.peek();
+
+        if token.0 != TodoTokens::EndOfLine {
+            self.position += 1;
+        }
+
+        token
+    }
 
-        token_iter.by_ref().next(); // This will be a field separator, so ignore
+    fn expect(&mut self, expected: TodoTokens) -> Result<(), ParseError> {
+        let token = self.advance();
 
-        if let Some(TodoTokens::TodoValue(contents_field)) = token_iter.next() {
-            todo.contents = contents_field.clone();
+        if token.0 == expected {
+            Ok(())
+        } else {
+            Err(unexpected(token, &expected.describe()))
+        }
+    }
+
+    fn parse_line(&mut self) -> Result<Todo, ParseError> {
+        let todo = self.parse_todo()?;
+
+        self.expect(TodoTokens::EndOfLine)?;
+
+        Ok(todo)
+    }
+
+    fn parse_todo(&mut self) -> Result<Todo, ParseError> {
+        let complete = match self.advance() {
+            (TodoTokens::TodoValue(val), _) if val == "0" => false,
+            (TodoTokens::TodoValue(val), _) if val == "1" => true,
+            token => return Err(unexpected(token, "completion flag (0 or 1)")),
         };
 
-        todo
-    } else {
-        // Nested todos included
-        let mut root = Todo::new(Some(false), "".to_string());
-
-        if let Some(complete) = token_iter.by_ref().next() {
-            if let TodoTokens::TodoValue(val) = complete {
-                root.complete = match val.as_str() {
-                    "0" => false,
-                    "1" => true,
-                    _ => unreachable!(),
-                };
+        self.expect(TodoTokens::FieldSeparator)?;
+
+        let contents = match self.peek() {
+            (TodoTokens::TodoValue(val), _) => {
+                self.advance();
+                val.clone()
             }
-        }
+            // Empty contents of a sub todo are not tokenized as a value, the bar follows directly
+            (TodoTokens::FieldSeparator, _) => "".to_string(),
+            token => return Err(unexpected(token, "contents")),
+        };
+
+        // Not Todo::new, a todo read without a creation time should not get one
+        let mut todo = Todo {
+            complete,
+            contents,
+            ..Default::default()
+        };
+
+        self.expect(TodoTokens::FieldSeparator)?;
 
-        token_iter.by_ref().next(); // Skip field separator
+        while let (TodoTokens::TodoValue(attribute), column) = self.peek() {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
 
-        if let Some(contents) = token_iter.by_ref().next() {
-            if let TodoTokens::TodoValue(val) = contents {
-                root.contents = val.clone();
+            if let Err(expected) = todo.set_attribute(key, value) {
+                return Err(unexpected(
+                    &(TodoTokens::TodoValue(attribute.clone()), *column),
+                    expected,
+                ));
             }
+
+            self.advance();
+            self.expect(TodoTokens::FieldSeparator)?;
+        }
+
+        if self.peek().0 == TodoTokens::TodoArrBeg {
+            self.advance();
+
+            todo.sub_todos = self.parse_sub_todos()?;
+
+            self.expect(TodoTokens::TodoArrEnd)?;
         }
 
-        // Here, there is at least 1 TodoArrBeg and TodoArrEnd, there might be more, i.e. nested todos within the already nested todos, the
-        // children variable here contains all remaing tokens after having parsed the first few above
-        token_iter.next(); // Skip FieldSeparator
-        token_iter.next(); // Skip TodoArrBeg
-        token_iter.next_back(); // Skip TodoArrEnd
+        Ok(todo)
+    }
+
+    fn parse_sub_todos(&mut self) -> Result<Vec<Todo>, ParseError> {
+        let mut sub_todos = Vec::new();
+
+        if self.peek().0 == TodoTokens::TodoArrEnd {
+            return Ok(sub_todos);
+        }
 
-        let sub_todos = token_iter.map(|e| e.clone()).collect::<Vec<TodoTokens>>();
+        sub_todos.push(self.parse_todo()?);
 
-        let children = sub_todos.split(|e| *e == TodoTokens::TodoSeparator);
+        while self.peek().0 == TodoTokens::TodoSeparator {
+            self.advance();
 
-        for child in children {
-            root.sub_todos.push(todo_from_tokens(child.to_vec()));
+            sub_todos.push(self.parse_todo()?);
         }
 
-        root
+        match self.peek() {
+            (TodoTokens::TodoArrEnd, _) => Ok(sub_todos),
+            token => Err(unexpected(token, "% or ]")),
+        }
     }
 }
 
-// Format of Todo with no sub_todos: TodoValue, FieldSeparator, TodoValue, FieldSeparator
-// Format of Todo with sub_todos: TodoValue, FieldSeparator, TodoValue, FieldSeparator, TodoArrBeg, ..., TodoArrEnd
-pub fn from_todo_string(todo_str: String) -> Todo {
-    let raw_tokens = tokenize_todo_string(&todo_str);
+pub fn from_todo_string(todo_str: String) -> Result<Todo, ParseError> {
+    from_versioned_todo_string(&todo_str, FormatVersion::V2)
+}
 
-    todo_from_tokens(raw_tokens)
+pub fn from_versioned_todo_string(
+    todo_str: &str,
+    version: FormatVersion,
+) -> Result<Todo, ParseError> {
+    let raw_tokens = tokenize_todo_string(todo_str, version);
+
+    TodoParser::new(&raw_tokens).parse_line()
+}
+
+pub fn from_todos_file_string(file_str: &str) -> Result<Vec<Todo>, ParseError> {
+    //! Loads every todo in the contents of a .todos file, files without the header are
+    //! read as version 1 so lists written before escaping was introduced still load.
+    let mut lines = file_str.lines().enumerate().peekable();
+
+    let version = match lines.next_if(|(_, l)| *l == TODOS_HEADER) {
+        Some(_) => FormatVersion::V2,
+        None => FormatVersion::V1,
+    };
+
+    lines
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(ind, s)| {
+            from_versioned_todo_string(s, version).map_err(|e| ParseError { line: ind + 1, ..e })
+        })
+        .collect()
+}
+
+pub fn to_todos_file_string(todos_list: &[Todo]) -> String {
+    let mut file_str = format!("{TODOS_HEADER}\n");
+
+    for todo in todos_list {
+        file_str.push_str(&todo.to_todos());
+        file_str.push('\n');
+    }
+
+    file_str
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn unstamped(complete: Option<bool>, contents: &str) -> Todo {
+        //! A todo without a creation time, so it is written the same whenever the test runs
+        Todo {
+            created: None,
+            ..Todo::new(complete, contents.to_string())
+        }
+    }
+
     #[test]
     fn from_todo_to_string_no_sub_todos() {
         let example = Todo::new(Some(false), "Something".to_string());
@@ -331,27 +768,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_todo_to_enumerated_string_w_nested_sub_todos() {
+        let mut example = Todo::new(Some(false), "Something".to_string());
+
+        example
+            .sub_todos
+            .push(Todo::new(Some(false), "This is a test".to_string()));
+
+        example.sub_todos[0]
+            .sub_todos
+            .push(Todo::new(Some(true), "This is a nested test".to_string()));
+
+        assert_eq!(
+            "[~] - Something\n    1. [~] - This is a test\n        1. [X] - This is a nested test",
+            example.to_enumerated_string(None)
+        );
+    }
+
     #[test]
     fn from_todo_to_todos_no_sub_todos() {
-        let example = Todo::new(Some(false), "Something".to_string());
+        let example = unstamped(Some(false), "Something");
         assert_eq!("0|Something|", example.to_todos());
     }
 
     #[test]
     fn from_todo_to_todos_w_sub_todos() {
-        let mut example = Todo::new(Some(false), "Something".to_string());
+        let mut example = unstamped(Some(false), "Something");
 
         example
             .sub_todos
-            .push(Todo::new(Some(true), "This is a test".to_string()));
+            .push(unstamped(Some(true), "This is a test"));
 
         example
             .sub_todos
-            .push(Todo::new(Some(true), "This is a test".to_string()));
+            .push(unstamped(Some(true), "This is a test"));
 
         example
             .sub_todos
-            .push(Todo::new(Some(true), "This is a test".to_string()));
+            .push(unstamped(Some(true), "This is a test"));
 
         assert_eq!(
             "0|Something|[1|This is a test|%1|This is a test|%1|This is a test|]",
@@ -365,9 +820,10 @@ mod tests {
             Todo {
                 complete: false,
                 contents: "Empty".to_string(),
-                sub_todos: Vec::new()
+                sub_todos: Vec::new(),
+                ..Default::default()
             },
-            from_todo_string("0|Empty|".to_string())
+            from_todo_string("0|Empty|".to_string()).unwrap()
         );
     }
 
@@ -380,10 +836,12 @@ mod tests {
                 sub_todos: vec![Todo {
                     complete: true,
                     contents: "This is a sub_todo".to_string(),
-                    sub_todos: Vec::new()
-                }]
+                    sub_todos: Vec::new(),
+                    ..Default::default()
+                }],
+                ..Default::default()
             },
-            from_todo_string("0|One sub|[1|This is a sub_todo|]".to_string())
+            from_todo_string("0|One sub|[1|This is a sub_todo|]".to_string()).unwrap()
         );
     }
 
@@ -396,13 +854,21 @@ mod tests {
                 sub_todos: vec![Todo {
                     complete: true,
                     contents: "This is a sub_todo".to_string(),
-                    sub_todos: Vec::new()
-                }]
+                    sub_todos: vec![Todo {
+                        complete: true,
+                        contents: "This is an even further nested todo".to_string(),
+                        sub_todos: Vec::new(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
             },
             from_todo_string(
                 "0|One sub|[1|This is a sub_todo|[1|This is an even further nested todo|]]"
                     .to_string()
             )
+            .unwrap()
         );
     }
 
@@ -412,16 +878,390 @@ mod tests {
             Todo {
                 complete: false,
                 contents: "One sub".to_string(),
-                sub_todos: vec![Todo {
-                    complete: true,
-                    contents: "This is a sub_todo".to_string(),
-                    sub_todos: Vec::new()
-                }]
+                sub_todos: vec![
+                    Todo {
+                        complete: true,
+                        contents: "This is a sub_todo".to_string(),
+                        sub_todos: Vec::new(),
+                        ..Default::default()
+                    },
+                    Todo {
+                        complete: true,
+                        contents: "This is an even further nested todo".to_string(),
+                        sub_todos: Vec::new(),
+                        ..Default::default()
+                    }
+                ],
+                ..Default::default()
             },
             from_todo_string(
                 "0|One sub|[1|This is a sub_todo|%1|This is an even further nested todo|]"
                     .to_string()
             )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn from_todo_string_w_siblings_w_sub_todos() {
+        let todo = from_todo_string("0|Root|[0|A|[1|A1|%0|A2|]%0|B|[0||]]".to_string()).unwrap();
+
+        assert_eq!(2, todo.sub_todos.len());
+        assert_eq!(2, todo.sub_todos[0].sub_todos.len());
+        assert_eq!("A2", todo.sub_todos[0].sub_todos[1].contents);
+        assert_eq!("B", todo.sub_todos[1].contents);
+        assert_eq!("", todo.sub_todos[1].sub_todos[0].contents);
+    }
+
+    #[test]
+    fn from_todo_to_todos_escapes_special_characters() {
+        let example = unstamped(Some(false), "fix a[0] | b % c \\ d\ne");
+        assert_eq!("0|fix a\\[0\\] \\| b \\% c \\\\ d\\ne|", example.to_todos());
+    }
+
+    #[test]
+    fn from_todo_string_w_escaped_special_characters() {
+        let todo = from_todo_string("0|fix a\\[0\\] \\| b|[1|50\\% done|]".to_string()).unwrap();
+
+        assert_eq!("fix a[0] | b", todo.contents);
+        assert_eq!(1, todo.sub_todos.len());
+        assert_eq!("50% done", todo.sub_todos[0].contents);
+    }
+
+    #[test]
+    fn from_todos_file_string_reads_unescaped_files() {
+        let todos = from_todos_file_string("0|C:\\Users\\me|\n1|Another|[0|Sub|]\n").unwrap();
+
+        assert_eq!(2, todos.len());
+        assert_eq!("C:\\Users\\me", todos[0].contents);
+        assert_eq!("Sub", todos[1].sub_todos[0].contents);
+    }
+
+    #[test]
+    fn from_todo_to_todos_w_priority() {
+        let mut example = unstamped(Some(false), "Something");
+        example.priority = Some(Priority::B);
+        example.sub_todos.push(unstamped(None, "Sub"));
+
+        assert_eq!("0|Something|p=B|[0|Sub|]", example.to_todos());
+        assert_eq!("[ ] - (B) Something\n- [ ] - Sub", example.to_string());
+    }
+
+    #[test]
+    fn from_todo_string_w_priority() {
+        let todo = from_todo_string("0|Something|p=A|[1|Sub|p=C|]".to_string()).unwrap();
+
+        assert_eq!(Some(Priority::A), todo.priority);
+        assert_eq!(Some(Priority::C), todo.sub_todos[0].priority);
+    }
+
+    #[test]
+    fn from_todo_string_w_unknown_attribute() {
+        assert_eq!(
+            Err(ParseError {
+                line: 1,
+                column: 13,
+                expected: "priority (A, B or C)".to_string(),
+                found: "\"p=Z\"".to_string()
+            }),
+            from_todo_string("0|Something|p=Z|".to_string())
+        );
+        assert_eq!(
+            "attribute (id, p, due, tag, ctx, created, done or note)",
+            from_todo_string("0|Something|x=1|".to_string())
+                .unwrap_err()
+                .expected
+        );
+    }
+
+    #[test]
+    fn from_todo_string_w_due_date() {
+        let todo = from_todo_string("0|Something|p=A|due=2026-11-01|".to_string()).unwrap();
+
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 11, 1), todo.due);
+        assert_eq!("0|Something|p=A|due=2026-11-01|", todo.to_todos());
+        assert_eq!("(A) Something due:2026-11-01", todo.label());
+        assert!(todo.is_overdue(NaiveDate::from_ymd_opt(2026, 11, 2).unwrap()));
+        assert!(!todo.is_overdue(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap()));
+    }
+
+    #[test]
+    fn from_todo_string_w_times() {
+        let todo = from_todo_string(
+            "1|Something|created=2026-10-01T09:30:00|done=2026-10-03T17:00:05|".to_string(),
+        )
+        .unwrap();
+
+        let created = NaiveDate::from_ymd_opt(2026, 10, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap();
+
+        assert_eq!(Some(created), todo.created);
+        assert_eq!(
+            "[X] - Something (done 1d ago)",
+            todo.to_enumerated_string_with(
+                None,
+                &DisplayOptions {
+                    ages: Some(created + chrono::Duration::days(4)),
+                    ..Default::default()
+                }
+            )
+        );
+        assert!(from_todo_string("0|Something|created=yesterday|".to_string()).is_err());
+    }
+
+    #[test]
+    fn from_todo_to_todos_w_notes() {
+        let mut example = unstamped(None, "Something");
+        example.notes = Some("See https://example.com/a=b|c\n\n- done when it works".to_string());
+
+        assert_eq!(
+            "0|Something|note=See https://example.com/a=b\\|c\\n\\n- done when it works|",
+            example.to_todos()
+        );
+        assert_eq!(example, from_todo_string(example.to_todos()).unwrap());
+    }
+
+    #[test]
+    fn completion_times() {
+        let mut todo = Todo::new(None, "Something".to_string());
+        let today = date::today();
+
+        assert!(todo.created.is_some());
+        assert!(!todo.completed_between(None, None));
+
+        todo.mark_complete(true);
+        let completed = todo.completed;
+
+        assert!(completed.is_some());
+        assert!(todo.completed_between(Some(today), Some(today)));
+        assert!(!todo.completed_between(today.succ_opt(), None));
+        assert!(!todo.completed_between(None, today.pred_opt()));
+
+        todo.mark_complete(true);
+        assert_eq!(completed, todo.completed);
+
+        todo.mark_complete(false);
+        assert_eq!(None, todo.completed);
+    }
+
+    #[test]
+    fn from_text_w_tags() {
+        let mut todo = Todo::from_text(None, "Fix +backend login  bug @office +api a+b @");
+        todo.created = None;
+
+        assert_eq!("Fix login  bug a+b @", todo.contents);
+        assert_eq!(
+            vec!["api", "backend"],
+            todo.tags.iter().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert!(todo.contexts.contains("office"));
+        assert_eq!("Fix login  bug a+b @ +api +backend @office", todo.label());
+        assert_eq!(
+            "0|Fix login  bug a+b @|tag=api|tag=backend|ctx=office|",
+            todo.to_todos()
+        );
+        assert_eq!(todo, from_todo_string(todo.to_todos()).unwrap());
+    }
+
+    #[test]
+    fn from_text_keeps_text_as_typed() {
+        let cases = [
+            ("two\nlines +web", "two\nlines"),
+            ("keep   spacing @home", "keep   spacing"),
+            ("+web Fix login", "Fix login"),
+            ("Fix +web\n  indented", "Fix\n  indented"),
+            ("+1 for this, 2 @ 3", "+1 for this, 2 @ 3"),
+        ];
+
+        for (text, contents) in cases {
+            let todo = Todo::from_text(None, text);
+            assert_eq!(contents, todo.contents);
+
+            let loaded = from_todos_file_string(&to_todos_file_string(&[todo])).unwrap();
+            assert_eq!(contents, loaded[0].contents);
+        }
+
+        assert!(Todo::from_text(None, "+1 for this").tags.is_empty());
+    }
+
+    #[test]
+    fn from_todo_string_w_id() {
+        let todo = from_todo_string("0|Something|id=k3x9a|[0|Sub|]".to_string()).unwrap();
+
+        assert_eq!(Some("k3x9a".to_string()), todo.id);
+        assert_eq!("Something", todo.label());
+        assert_eq!(
+            "[ ] - Something #k3x9a\n    1. [ ] - Sub",
+            todo.to_enumerated_string_with(
+                None,
+                &DisplayOptions {
+                    ids: true,
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn to_todos_file_string_writes_header() {
+        let todos = vec![unstamped(Some(true), "a|b")];
+
+        assert_eq!("#whatodo 2\n1|a\\|b|\n", to_todos_file_string(&todos));
+    }
+
+    fn arb_leaf() -> impl proptest::strategy::Strategy<Value = Todo> {
+        use proptest::prelude::*;
+
+        let priority = prop_oneof![Just(Priority::A), Just(Priority::B), Just(Priority::C)];
+        let time = || {
+            proptest::option::of((0i64..4_000_000_000).prop_map(|secs| {
+                chrono::DateTime::from_timestamp(secs, 0)
+                    .unwrap()
+                    .naive_utc()
+            }))
+        };
+        let due = (0i64..40_000).prop_map(|days| {
+            NaiveDate::from_ymd_opt(1970, 1, 1).unwrap() + chrono::Duration::days(days)
+        });
+        // Tags and contexts are written as attribute values, so they get the characters which
+        // have a meaning there, and new lines, as often as anything else
+        let words = || {
+            proptest::collection::btree_set(
+                prop_oneof!["[a-z=| %\\[\\]\\\\\n]*", "(\\PC|\n)*"],
+                0..3,
+            )
+        };
+
+        (
+            (
+                any::<bool>(),
+                "(\\PC|\n)*",
+                proptest::option::of("[a-z2-9]{5}"),
+                proptest::option::of(priority),
+                proptest::option::of(due),
+            ),
+            (words(), words()),
+            (time(), time(), proptest::option::of("(\\PC|\n)*")),
+        )
+            .prop_map(
+                |(
+                    (complete, contents, id, priority, due),
+                    (tags, contexts),
+                    (created, completed, notes),
+                )| Todo {
+                    id,
+                    complete,
+                    contents,
+                    priority,
+                    due,
+                    tags,
+                    contexts,
+                    created,
+                    completed,
+                    notes,
+                    ..Default::default()
+                },
+            )
+    }
+
+    fn arb_todo() -> impl proptest::strategy::Strategy<Value = Todo> {
+        use proptest::prelude::*;
+
+        arb_leaf().prop_recursive(5, 64, 4, |inner| {
+            (arb_leaf(), proptest::collection::vec(inner, 1..4)).prop_map(
+                |(mut todo, sub_todos)| {
+                    todo.sub_todos = sub_todos;
+                    todo
+                },
+            )
+        })
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn nested_todos_round_trip(todo in arb_todo()) {
+            proptest::prop_assert_eq!(&todo, &from_todo_string(todo.to_todos()).unwrap());
+        }
+
+        #[test]
+        fn nested_todos_file_round_trip(todos in proptest::collection::vec(arb_todo(), 0..5)) {
+            proptest::prop_assert_eq!(
+                &todos,
+                &from_todos_file_string(&to_todos_file_string(&todos)).unwrap()
+            );
+        }
+
+        #[test]
+        fn escaped_contents_round_trip(contents in "\\PC*", child in "\\PC+", complete: bool) {
+            let mut example = Todo::new(Some(complete), contents.clone());
+            example.sub_todos.push(Todo::new(Some(!complete), child.clone()));
+
+            let loaded = from_todos_file_string(&to_todos_file_string(&[example])).unwrap();
+
+            proptest::prop_assert_eq!(1, loaded.len());
+            proptest::prop_assert_eq!(complete, loaded[0].complete);
+            proptest::prop_assert_eq!(&contents, &loaded[0].contents);
+            proptest::prop_assert_eq!(1, loaded[0].sub_todos.len());
+            proptest::prop_assert_eq!(!complete, loaded[0].sub_todos[0].complete);
+            proptest::prop_assert_eq!(&child, &loaded[0].sub_todos[0].contents);
+        }
+
+        #[test]
+        fn escaped_contents_w_new_lines_round_trip(contents in "[a-z|%\\[\\]\\\\\n\r]*") {
+            let example = Todo::new(None, contents.clone());
+
+            proptest::prop_assert_eq!(contents, from_todo_string(example.to_todos()).unwrap().contents);
+        }
+    }
+
+    #[test]
+    fn from_todo_string_w_invalid_complete_field() {
+        assert_eq!(
+            Err(ParseError {
+                line: 1,
+                column: 1,
+                expected: "completion flag (0 or 1)".to_string(),
+                found: "\"yes\"".to_string()
+            }),
+            from_todo_string("yes|Something|".to_string())
         );
     }
+
+    #[test]
+    fn from_todo_string_w_missing_bar() {
+        assert_eq!(
+            Err(ParseError {
+                line: 1,
+                column: 12,
+                expected: "|".to_string(),
+                found: "end of line".to_string()
+            }),
+            from_todo_string("0|Something".to_string())
+        );
+    }
+
+    #[test]
+    fn from_todo_string_w_unclosed_sub_todos() {
+        assert_eq!(
+            Err(ParseError {
+                line: 1,
+                column: 20,
+                expected: "% or ]".to_string(),
+                found: "end of line".to_string()
+            }),
+            from_todo_string("0|Something|[1|Sub|".to_string())
+        );
+    }
+
+    #[test]
+    fn from_todos_file_string_reports_line() {
+        let err =
+            from_todos_file_string("#whatodo 2\n0|Fine|\n\n0|Fine|[2|Broken|]\n").unwrap_err();
+
+        assert_eq!(4, err.line);
+        assert_eq!(9, err.column);
+        assert_eq!("completion flag (0 or 1)", err.expected);
+    }
 }
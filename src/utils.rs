@@ -1,67 +1,93 @@
 use crate::todo::Todo;
-use std::iter::Peekable;
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    hash::BuildHasher,
+};
+
+// Without 0/o and 1/l, which are easily confused
+const ID_ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+const ID_LENGTH: usize = 5;
 
 pub fn get_mut_from_num_depth<'a>(
     todos_list: &'a mut Vec<Todo>,
-    num_depth: &Vec<usize>,
+    num_depth: &[usize],
 ) -> Option<&'a mut Todo> {
     let mut depth_finder = num_depth.iter();
 
-    let final_index = depth_finder.next_back().unwrap();
+    let final_index = depth_finder.next_back()?;
 
     let mut curr_root = todos_list;
 
-    while let Some(ind) = depth_finder.next() {
-        match curr_root.get_mut(*ind) {
-            Some(node) => curr_root = &mut node.sub_todos,
-            None => {
-                eprintln!("Index is out of bounds, could not complete todo");
-                return None;
-            }
-        }
+    for ind in depth_finder {
+        curr_root = &mut curr_root.get_mut(*ind)?.sub_todos;
     }
 
     curr_root.get_mut(*final_index)
 }
 
-pub fn depth_iterator_from_args_to_item<'a, I: Iterator<Item = &'a String>>(
-    mut num_depth: Peekable<I>,
-) -> Vec<usize> {
-    //! Returns a vector which will contain the path to the individual item.
-    let mut indices: Vec<usize> = Vec::new();
-
-    while let Some(ind) = num_depth.next_if(|i| i.parse::<usize>().is_ok()) {
-        // Account for user not using 0-indexing
-        indices.push(ind.parse::<usize>().unwrap() - 1);
+pub fn search_all_todos_content(todos_list: &Vec<Todo>, needle: &str) -> bool {
+    for todo in todos_list {
+        if todo.contents == needle || search_all_todos_content(&todo.sub_todos, needle) {
+            return true;
+        }
     }
 
-    indices
+    false
 }
 
-pub fn depth_iterator_from_args_to_parent<'a, I: Iterator<Item = &'a String>>(
-    mut num_depth: Peekable<I>,
-) -> Vec<usize> {
-    //! Returns a vector which will contain the path to the list in which the
-    //! item resides.
+pub fn generate_id(mut seed: u64, existing: &HashSet<String>) -> String {
+    //! Creates an id from seed which is not in existing. Ids are short, made of lowercase letters
+    //! and digits, and always start with a letter so they can never be mistaken for an index.
+    loop {
+        let mut id = String::with_capacity(ID_LENGTH);
+        let mut bits = seed;
 
-    let mut indices: Vec<usize> = Vec::new();
+        // The first character only picks from the letters at the start of the alphabet
+        id.push(ID_ALPHABET[(bits % 24) as usize] as char);
+        bits /= 24;
 
-    while let Some(ind) = num_depth.next_if(|i| i.parse::<usize>().is_ok()) {
-        // Account for user not using 0-indexing
-        indices.push(ind.parse::<usize>().unwrap() - 1);
+        for _ in 1..ID_LENGTH {
+            id.push(ID_ALPHABET[(bits % ID_ALPHABET.len() as u64) as usize] as char);
+            bits /= ID_ALPHABET.len() as u64;
+        }
+
+        if !existing.contains(&id) {
+            return id;
+        }
+
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
     }
+}
 
-    indices.pop(); // Remove the back, because the back refers to the individual item
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    //! 64 bit FNV-1a hash of bytes, which unlike the hashers in std never changes between
+    //! releases of Rust
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
 
-    indices
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
 }
 
-pub fn search_all_todos_content(todos_list: &Vec<Todo>, needle: &str) -> bool {
-    for todo in todos_list {
-        if todo.contents == needle || search_all_todos_content(&todo.sub_todos, needle) {
-            return true;
+pub fn random_seed() -> u64 {
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
+
+pub fn find_id(todos_list: &[Todo], id: &str) -> Option<Vec<usize>> {
+    //! 0 indexed path of the todo with the given id
+    for (ind, todo) in todos_list.iter().enumerate() {
+        if todo.id.as_deref() == Some(id) {
+            return Some(vec![ind]);
+        }
+
+        if let Some(mut path) = find_id(&todo.sub_todos, id) {
+            path.insert(0, ind);
+            return Some(path);
         }
     }
 
-    false
+    None
 }
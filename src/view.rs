@@ -0,0 +1,201 @@
+use crate::{
+    date::DISPLAY_TIME_FORMAT,
+    path::TodoPath,
+    todo::{DisplayOptions, Todo},
+};
+
+/// A todo picked for display, it remembers the path it is stored at so filtered and sorted
+/// listings still show the indices the other commands expect.
+#[derive(Debug)]
+pub struct ViewNode<'a> {
+    /// 0 indexed path of the todo in its list
+    pub path: Vec<usize>,
+    pub todo: &'a Todo,
+    pub children: Vec<ViewNode<'a>>,
+}
+
+fn tree_from<'a>(todos: &'a [Todo], parent: &[usize]) -> Vec<ViewNode<'a>> {
+    todos
+        .iter()
+        .enumerate()
+        .map(|(ind, todo)| {
+            let mut path = parent.to_vec();
+            path.push(ind);
+
+            ViewNode {
+                children: tree_from(&todo.sub_todos, &path),
+                path,
+                todo,
+            }
+        })
+        .collect()
+}
+
+pub fn tree(todos: &[Todo]) -> Vec<ViewNode<'_>> {
+    //! Every todo of the list, in the order they are stored
+    tree_from(todos, &[])
+}
+
+pub fn prune<'a, F: Fn(&Todo) -> bool>(nodes: Vec<ViewNode<'a>>, keep: &F) -> Vec<ViewNode<'a>> {
+    //! Keeps the todos for which keep returns true, along with their parents so each kept todo
+    //! is still shown where it lives in the tree.
+    nodes
+        .into_iter()
+        .filter_map(|mut node| {
+            node.children = prune(node.children, keep);
+
+            match keep(node.todo) || !node.children.is_empty() {
+                true => Some(node),
+                false => None,
+            }
+        })
+        .collect()
+}
+
+pub fn sort_by_key<K: Ord, F: Fn(&Todo) -> K>(nodes: &mut [ViewNode], key: &F) {
+    //! Sorts every level of the tree, todos with equal keys keep their stored order
+    nodes.sort_by_key(|node| key(node.todo));
+
+    for node in nodes.iter_mut() {
+        sort_by_key(&mut node.children, key);
+    }
+}
+
+pub fn sort_by_priority(nodes: &mut [ViewNode]) {
+    //! Most important first, todos without a priority last
+    sort_by_key(nodes, &|todo| (todo.priority.is_none(), todo.priority));
+}
+
+pub fn render(nodes: &[ViewNode]) -> String {
+    render_with(nodes, &DisplayOptions::default())
+}
+
+pub fn render_with(nodes: &[ViewNode], options: &DisplayOptions) -> String {
+    let mut lines = Vec::new();
+
+    render_into(nodes, options, &mut lines);
+
+    lines.join("\n")
+}
+
+fn render_into(nodes: &[ViewNode], options: &DisplayOptions, lines: &mut Vec<String>) {
+    for node in nodes {
+        // The whole path is shown, so what is printed is what other commands take
+        lines.push(format!(
+            "{}{} [{}] - {}",
+            "    ".repeat(node.path.len() - 1),
+            TodoPath::from_indices(&node.path),
+            node.todo.marker(),
+            node.todo.label_with(options)
+        ));
+
+        render_into(&node.children, options, lines);
+    }
+}
+
+pub fn render_details(path: &[usize], todo: &Todo) -> String {
+    //! Everything known about a single todo, followed by its notes
+    let mut lines = vec![
+        format!("[{}] - {}", todo.marker(), todo.label()),
+        format!("path:      {}", TodoPath::from_indices(path)),
+    ];
+
+    if let Some(id) = &todo.id {
+        lines.push(format!("id:        {id}"));
+    }
+
+    if let Some(created) = todo.created {
+        lines.push(format!(
+            "created:   {}",
+            created.format(DISPLAY_TIME_FORMAT)
+        ));
+    }
+
+    if let Some(completed) = todo.completed {
+        lines.push(format!(
+            "completed: {}",
+            completed.format(DISPLAY_TIME_FORMAT)
+        ));
+    }
+
+    if !todo.sub_todos.is_empty() {
+        lines.push(format!(
+            "sub todos: {} of {} complete",
+            todo.sub_todos.iter().filter(|t| t.complete).count(),
+            todo.sub_todos.len()
+        ));
+    }
+
+    if let Some(notes) = &todo.notes {
+        lines.push(String::new());
+        lines.push(notes.clone());
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::Priority;
+
+    fn example_todos() -> Vec<Todo> {
+        let mut first = Todo::new(None, "First".to_string());
+        first.sub_todos.push(Todo::new(None, "Low".to_string()));
+        first.sub_todos.push(Todo::new(None, "High".to_string()));
+        first.sub_todos[0].priority = Some(Priority::C);
+        first.sub_todos[1].priority = Some(Priority::A);
+
+        let mut second = Todo::new(None, "Second".to_string());
+        second.priority = Some(Priority::B);
+
+        vec![first, second]
+    }
+
+    #[test]
+    fn render_shows_full_paths() {
+        let todos = example_todos();
+
+        assert_eq!(
+            "1 [ ] - First\n    1.1 [ ] - (C) Low\n    1.2 [ ] - (A) High\n2 [ ] - (B) Second",
+            render(&tree(&todos))
+        );
+    }
+
+    #[test]
+    fn details() {
+        let mut todos = example_todos();
+        todos[0].id = Some("k3x9a".to_string());
+        todos[0].sub_todos[0].complete = true;
+        todos[0].notes = Some("Line one\nLine two".to_string());
+        todos[0].created = chrono::NaiveDate::from_ymd_opt(2026, 10, 1)
+            .unwrap()
+            .and_hms_opt(9, 30, 0);
+
+        assert_eq!(
+            "[~] - First\npath:      1\nid:        k3x9a\ncreated:   2026-10-01 09:30:00\nsub todos: 1 of 2 complete\n\nLine one\nLine two",
+            render_details(&[0], &todos[0])
+        );
+    }
+
+    #[test]
+    fn prune_keeps_parents() {
+        let todos = example_todos();
+        let nodes = prune(tree(&todos), &|t| t.priority == Some(Priority::A));
+
+        assert_eq!("1 [ ] - First\n    1.2 [ ] - (A) High", render(&nodes));
+    }
+
+    #[test]
+    fn sort_keeps_paths() {
+        let todos = example_todos();
+        let mut nodes = tree(&todos);
+
+        sort_by_priority(&mut nodes);
+
+        assert_eq!(
+            "2 [ ] - (B) Second\n1 [ ] - First\n    1.2 [ ] - (A) High\n    1.1 [ ] - (C) Low",
+            render(&nodes)
+        );
+    }
+}